@@ -0,0 +1,46 @@
+//! Edit costs on a large note. Run with `cargo bench --bench buffer`.
+//!
+//! Edits shift and re-encrypt the pinned buffer in place from the edit
+//! point, so they are linear in the note size but stay well under a
+//! millisecond at 50KB. A rope would make them logarithmic, but its nodes
+//! are separate heap allocations that can't be pinned or reliably zeroed.
+
+use amnesia::mem_buffer::MemoryBuffer;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+const NOTE_LEN: usize = 50 * 1024;
+
+fn note(key: Option<[u8; 32]>) -> MemoryBuffer {
+    let mut buffer = MemoryBuffer::new_lenient(64 * 1024, key);
+    let line = "The quick brown fox jumps over the lazy dog.\n";
+    buffer.update(&line.repeat(NOTE_LEN / line.len()));
+    buffer
+}
+
+fn edits(c: &mut Criterion) {
+    for (name, key) in [("plain", None), ("stealth", Some([7u8; 32]))] {
+        let middle = note(key).char_len() / 2;
+
+        c.bench_function(&format!("insert_char_50kb_{}", name), |b| {
+            b.iter_batched_ref(
+                || note(key),
+                |buffer| buffer.insert_char(black_box(middle), 'x'),
+                BatchSize::LargeInput,
+            )
+        });
+        c.bench_function(&format!("remove_char_50kb_{}", name), |b| {
+            b.iter_batched_ref(
+                || note(key),
+                |buffer| buffer.remove_char(black_box(middle)),
+                BatchSize::LargeInput,
+            )
+        });
+        c.bench_function(&format!("to_string_50kb_{}", name), |b| {
+            let buffer = note(key);
+            b.iter(|| black_box(buffer.to_string()))
+        });
+    }
+}
+
+criterion_group!(benches, edits);
+criterion_main!(benches);
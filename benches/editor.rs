@@ -0,0 +1,40 @@
+//! Cursor movement and drawing on a large note. Run with
+//! `cargo bench --bench editor`.
+
+use amnesia::tui_app::Editor;
+use criterion::{criterion_group, criterion_main, Criterion};
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+
+const NOTE_LEN: usize = 50 * 1024;
+
+fn large_note() -> Editor {
+    let mut editor = Editor::new(None, None, None, false);
+    let line = "The quick brown fox jumps over the lazy dog.\n";
+    editor.insert_str(&line.repeat(NOTE_LEN / line.len()));
+    editor.cursor_position = editor.storage.byte_len() / 2;
+    editor
+}
+
+fn movement(c: &mut Criterion) {
+    let mut editor = large_note();
+    c.bench_function("move_up_down_50kb", |b| {
+        b.iter(|| {
+            editor.move_cursor_lineal(1);
+            editor.move_cursor_lineal(-1);
+        })
+    });
+
+    let mut editor = large_note();
+    let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+    c.bench_function("move_down_and_draw_50kb", |b| {
+        b.iter(|| {
+            editor.move_cursor_lineal(1);
+            editor.move_cursor_lineal(-1);
+            terminal.draw(|f| editor.draw(f)).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, movement);
+criterion_main!(benches);
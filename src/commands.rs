@@ -0,0 +1,226 @@
+use amnesia::config::Config;
+use amnesia::persistence::{self, KeySource, MIN_PASSWORD_LEN};
+use amnesia::secret::{into_string_zeroizing, read_all_zeroizing};
+use std::error::Error;
+use std::io;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroize;
+
+/// Prompts for a new password twice on the terminal and checks it.
+pub fn prompt_new_password() -> Result<String, Box<dyn Error>> {
+    let mut password = rpassword::prompt_password("Enter password: ")
+        .map_err(|e| format!("Failed to read password: {}", e))?;
+    let mut confirm = rpassword::prompt_password("Confirm password: ")
+        .map_err(|e| format!("Failed to read password: {}", e))?;
+    let matches = password == confirm;
+    confirm.zeroize();
+    if !matches {
+        password.zeroize();
+        return Err("Passwords don't match".into());
+    }
+    Ok(password)
+}
+
+/// `amnesia encrypt <output>`: encrypts stdin into an .amnesio file.
+/// With `password_stdin`, the first line of stdin is the password and the
+/// rest is the plaintext.
+pub fn encrypt(output: &Path, password_stdin: bool) -> Result<(), Box<dyn Error>> {
+    let bytes = read_all_zeroizing(io::stdin().lock())
+        .map_err(|e| format!("Failed to read stdin: {}", e))?;
+    let mut content = into_string_zeroizing(bytes);
+
+    let mut password = if password_stdin {
+        let (first, rest) = content.split_once('\n').unwrap_or((content.as_str(), ""));
+        let password = first.trim_end_matches('\r').to_string();
+        let rest = rest.to_string();
+        content.zeroize();
+        content = rest;
+        password
+    } else {
+        match prompt_new_password() {
+            Ok(p) => p,
+            Err(e) => {
+                content.zeroize();
+                return Err(e);
+            }
+        }
+    };
+
+    let result: Result<persistence::SaveInfo, String> = if password.len() < MIN_PASSWORD_LEN {
+        Err(format!(
+            "Password too short (min {} chars)",
+            MIN_PASSWORD_LEN
+        ))
+    } else {
+        persistence::save_encrypted(output, &content, &password, true)
+            .map_err(|e| format!("Failed to save file: {}", e))
+    };
+
+    let len = content.len();
+    password.zeroize();
+    content.zeroize();
+    let info = result?;
+
+    eprintln!("Encrypted {} bytes to {:?}", len, info.path);
+    Ok(())
+}
+
+/// `amnesia passwd <file>`: changes the password of an .amnesio file.
+pub fn passwd(path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut old_password = rpassword::prompt_password("Current password: ")
+        .map_err(|e| format!("Failed to read password: {}", e))?;
+    let mut new_password = match prompt_new_password() {
+        Ok(p) => p,
+        Err(e) => {
+            old_password.zeroize();
+            return Err(e);
+        }
+    };
+
+    let result: Result<(), String> = if new_password.len() < MIN_PASSWORD_LEN {
+        Err(format!(
+            "Password too short (min {} chars)",
+            MIN_PASSWORD_LEN
+        ))
+    } else {
+        persistence::rechange_password(path, &old_password, &new_password)
+            .map_err(|e| format!("Failed to change password: {}", e))
+    };
+
+    old_password.zeroize();
+    new_password.zeroize();
+    result?;
+
+    eprintln!("Password changed for {:?}", path);
+    Ok(())
+}
+
+/// `amnesia verify <file>`: checks the password and the file's integrity.
+pub fn verify(path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut password = rpassword::prompt_password("Enter password: ")
+        .map_err(|e| format!("Failed to read password: {}", e))?;
+    let result = persistence::verify(path, &password);
+    password.zeroize();
+    result.map_err(|e| format!("Verification failed: {}", e))?;
+
+    eprintln!("OK: {:?} is intact and the password is correct.", path);
+    Ok(())
+}
+
+/// `amnesia migrate <file>`: re-saves an older-format file in the current format.
+pub fn migrate(path: &Path) -> Result<(), Box<dyn Error>> {
+    let version = persistence::file_version(path).map_err(|e| format!("Cannot migrate: {}", e))?;
+    if version >= persistence::CURRENT_VERSION {
+        eprintln!("{:?} is already in a current format (v{}).", path, version);
+        return Ok(());
+    }
+
+    let mut password = rpassword::prompt_password("Enter password: ")
+        .map_err(|e| format!("Failed to read password: {}", e))?;
+    let result = persistence::migrate(path, &password);
+    password.zeroize();
+    result.map_err(|e| format!("Migration failed: {}", e))?;
+
+    eprintln!(
+        "Migrated {:?} from v{} to v{}.",
+        path,
+        version,
+        persistence::CURRENT_VERSION
+    );
+    Ok(())
+}
+
+/// `amnesia info <file>`: prints how a file was encrypted. Needs no password.
+pub fn info(path: &Path) -> Result<(), Box<dyn Error>> {
+    let info = persistence::file_info(path).map_err(|e| format!("{:?}: {}", path, e))?;
+
+    let older = if info.version < persistence::CURRENT_VERSION {
+        " (older format; `amnesia migrate` re-saves it)"
+    } else {
+        ""
+    };
+    println!("Format:  v{}{}", info.version, older);
+    let key = match info.key_source {
+        KeySource::Password => "password",
+        KeySource::Keyfile => "keyfile",
+        KeySource::Recipients => "age recipients",
+    };
+    println!("Key:     {}", key);
+    println!("Cipher:  {}", info.cipher);
+    if let Some(kdf) = info.assumed_kdf {
+        println!(
+            "KDF:     Argon2id, assumed defaults (m={} KiB, t={}, p={}; not stored in the file)",
+            kdf.memory_kib, kdf.iterations, kdf.parallelism
+        );
+    }
+    println!(
+        "Layout:  {}, header {}authenticated",
+        if info.chunked {
+            "64 KB frames"
+        } else {
+            "single message"
+        },
+        if info.header_authenticated {
+            ""
+        } else {
+            "not "
+        }
+    );
+    Ok(())
+}
+
+/// `amnesia export <file> <output>`: decrypts a note and writes it as HTML.
+/// This deliberately puts plaintext on disk, so it asks first unless `yes`.
+pub fn export(path: &Path, output: &Path, yes: bool) -> Result<(), Box<dyn Error>> {
+    let mut password = rpassword::prompt_password("Enter password: ")
+        .map_err(|e| format!("Failed to read password: {}", e))?;
+    let result = persistence::load_encrypted_with_metadata(path, &password);
+    password.zeroize();
+    let (mut content, metadata) = result.map_err(|e| format!("Failed to open file: {}", e))?;
+
+    if !yes && !confirm_plaintext_write(output) {
+        content.zeroize();
+        return Err("Export cancelled".into());
+    }
+
+    let title = metadata
+        .and_then(|m| m.title)
+        .unwrap_or_else(|| "amnesia note".to_string());
+    let result = persistence::write_atomic(output, |file| {
+        amnesia::export::write_html(file, &content, &title)
+    });
+    content.zeroize();
+    result.map_err(|e| format!("Failed to write {:?}: {}", output, e))?;
+
+    eprintln!("Exported {:?} to {:?} (unencrypted).", path, output);
+    Ok(())
+}
+
+/// `amnesia init-config [path]`: writes the commented default config file.
+/// Amnesia never writes it on its own.
+pub fn init_config(path: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let path = path
+        .or_else(Config::default_path)
+        .ok_or("No config directory on this platform; pass a path")?;
+    Config::write_default(&path).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => format!("{:?} already exists", path),
+        _ => format!("Failed to write {:?}: {}", path, e),
+    })?;
+
+    eprintln!("Wrote default config to {:?}", path);
+    Ok(())
+}
+
+fn confirm_plaintext_write(output: &Path) -> bool {
+    let overwrite = if output.exists() {
+        ", replacing it"
+    } else {
+        ""
+    };
+    eprint!(
+        "This writes the note UNENCRYPTED to {:?}{}. Continue? [y/N] ",
+        output, overwrite
+    );
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y")
+}
@@ -1,12 +1,146 @@
+use crate::keys::{self, Action, KeyBindings, KeySpec};
+use crate::tui_app::{Theme, DEFAULT_STATUS_FORMAT};
+use chrono::NaiveTime;
 use directories::ProjectDirs;
+use ratatui::style::Color;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// What happens when the idle timeout runs out.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IdleAction {
+    /// Wipe memory and exit.
+    #[default]
+    Wipe,
+    /// Hide the note until an unlock password is entered.
+    Lock,
+}
+
+/// The line-number gutter beside the editor.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LineNumbers {
+    #[default]
+    Off,
+    Absolute,
+    /// The cursor's line shows its number, the others their distance from it.
+    Relative,
+}
+
+impl LineNumbers {
+    /// The next mode when cycling: off, absolute, relative.
+    pub fn next(self) -> Self {
+        match self {
+            LineNumbers::Off => LineNumbers::Absolute,
+            LineNumbers::Absolute => LineNumbers::Relative,
+            LineNumbers::Relative => LineNumbers::Off,
+        }
+    }
+}
+
+/// Line endings written on save. The editor itself always uses LF;
+/// CRLF in pasted, piped or opened text is converted on the way in.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+/// The `[theme]` table. Colors are names (`"lightblue"`), hex (`"#1e1e2e"`)
+/// or 256-color indices (`"42"`); unset entries keep the built-in colors.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ThemeConfig {
+    pub editor_fg: Option<String>,
+    pub editor_bg: Option<String>,
+    pub status_fg: Option<String>,
+    pub status_bg: Option<String>,
+    pub border: Option<String>,
+    pub popup_border: Option<String>,
+    pub read_only: Option<String>,
+    pub stealth: Option<String>,
+    pub current_line: Option<String>,
+}
+
+impl ThemeConfig {
+    fn entries(&mut self) -> [(&'static str, &mut Option<String>); 9] {
+        [
+            ("editor_fg", &mut self.editor_fg),
+            ("editor_bg", &mut self.editor_bg),
+            ("status_fg", &mut self.status_fg),
+            ("status_bg", &mut self.status_bg),
+            ("border", &mut self.border),
+            ("popup_border", &mut self.popup_border),
+            ("read_only", &mut self.read_only),
+            ("stealth", &mut self.stealth),
+            ("current_line", &mut self.current_line),
+        ]
+    }
+
+    /// The theme with every valid color applied over the defaults.
+    pub fn to_theme(&self) -> Theme {
+        let mut theme = Theme::default();
+        let mut config = self.clone();
+        let targets = [
+            &mut theme.editor_fg,
+            &mut theme.editor_bg,
+            &mut theme.status_fg,
+            &mut theme.status_bg,
+            &mut theme.border,
+            &mut theme.popup_border,
+            &mut theme.read_only,
+            &mut theme.stealth,
+            &mut theme.current_line,
+        ];
+        for ((_, value), target) in config.entries().into_iter().zip(targets) {
+            if let Some(color) = value.as_deref().and_then(|c| c.parse::<Color>().ok()) {
+                *target = color;
+            }
+        }
+        theme
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub ttl: Option<f64>,
     pub idle: Option<f64>,
     pub stealth_encryption: Option<bool>,
+    pub panic_key: Option<String>,
+    pub max_load_attempts: Option<u32>,
+    pub tab_width: Option<usize>,
+    pub max_buffer_bytes: Option<usize>,
+    pub wrap: Option<bool>,
+    pub show_whitespace: Option<bool>,
+    pub line_numbers: Option<LineNumbers>,
+    pub redact: Option<bool>,
+    pub spell_check: Option<bool>,
+    pub dictionary: Option<String>,
+    pub read_only_output: Option<bool>,
+    pub scrub_on_overwrite: Option<bool>,
+    pub line_ending: Option<LineEnding>,
+    pub autosave: Option<String>,
+    pub autosave_interval: Option<f64>,
+    pub highlight_current_line: Option<bool>,
+    pub ruler: Option<bool>,
+    pub ruler_column: Option<usize>,
+    pub min_password_score: Option<u8>,
+    pub key_rotation: Option<f64>,
+    pub strict_memory: Option<bool>,
+    pub warn_on_swap: Option<bool>,
+    pub ttl_extension: Option<f64>,
+    pub max_ttl_extensions: Option<u32>,
+    pub on_idle: Option<IdleAction>,
+    pub status_format: Option<String>,
+    pub reading_wpm: Option<u32>,
+    pub status_timeout_secs: Option<f64>,
+    pub theme: Option<ThemeConfig>,
+    pub keys: Option<BTreeMap<String, String>>,
 }
 
 impl Default for Config {
@@ -15,31 +149,319 @@ impl Default for Config {
             ttl: Some(100.0),
             idle: Some(300.0),
             stealth_encryption: None,
+            panic_key: None,
+            max_load_attempts: None,
+            tab_width: None,
+            max_buffer_bytes: None,
+            wrap: None,
+            show_whitespace: None,
+            line_numbers: None,
+            redact: None,
+            spell_check: None,
+            dictionary: None,
+            read_only_output: None,
+            scrub_on_overwrite: None,
+            line_ending: None,
+            autosave: None,
+            autosave_interval: None,
+            highlight_current_line: None,
+            ruler: None,
+            ruler_column: None,
+            min_password_score: None,
+            key_rotation: None,
+            strict_memory: None,
+            warn_on_swap: None,
+            ttl_extension: None,
+            max_ttl_extensions: None,
+            on_idle: None,
+            status_format: None,
+            reading_wpm: None,
+            status_timeout_secs: None,
+            theme: None,
+            keys: None,
         }
     }
 }
 
 impl Config {
-    pub fn load() -> Self {
-        if let Some(proj_dirs) = ProjectDirs::from("com", "laticee", "amnesia") {
-            let config_dir = proj_dirs.config_dir();
-            let config_path = config_dir.join("config.toml");
-
-            if config_path.exists() {
-                if let Ok(content) = fs::read_to_string(&config_path) {
-                    if let Ok(config) = toml::from_str::<Config>(&content) {
-                        return config;
-                    } else {
-                        eprintln!(
-                            "Warning: Failed to parse config file at {:?}. Using defaults.",
-                            config_path
-                        );
-                    }
+    /// Resolves the idle timeout in seconds from CLI flags and this config:
+    /// 1. An explicit `--idle` always wins.
+    /// 2. Otherwise, `--ttl` given on the CLI turns the idle timeout off.
+    /// 3. Otherwise, the config value is used (the built-in default when no
+    ///    config file exists).
+    pub fn resolve_idle(&self, cli_idle: Option<f64>, cli_ttl: Option<f64>) -> Option<f64> {
+        if cli_idle.is_some() {
+            return cli_idle;
+        }
+        if cli_ttl.is_some() {
+            return None;
+        }
+        self.idle
+    }
+
+    /// Fills unset options with the values amnesia falls back to, so the
+    /// effective settings can be shown. Options whose absence means "off"
+    /// or "unlimited" (`ttl`, `idle`, `autosave`, ...) stay unset.
+    pub fn with_defaults(self) -> Self {
+        Self {
+            stealth_encryption: self.stealth_encryption.or(Some(false)),
+            tab_width: self.tab_width.or(Some(4)),
+            wrap: self.wrap.or(Some(false)),
+            show_whitespace: self.show_whitespace.or(Some(false)),
+            line_numbers: self.line_numbers.or(Some(LineNumbers::Off)),
+            redact: self.redact.or(Some(false)),
+            spell_check: self.spell_check.or(Some(false)),
+            read_only_output: self.read_only_output.or(Some(true)),
+            scrub_on_overwrite: self.scrub_on_overwrite.or(Some(false)),
+            line_ending: self.line_ending.or(Some(LineEnding::Lf)),
+            autosave_interval: self.autosave_interval.or(Some(60.0)),
+            highlight_current_line: self.highlight_current_line.or(Some(false)),
+            ruler: self.ruler.or(Some(false)),
+            ruler_column: self.ruler_column.or(Some(80)),
+            strict_memory: self.strict_memory.or(Some(false)),
+            warn_on_swap: self.warn_on_swap.or(Some(true)),
+            on_idle: self.on_idle.or(Some(IdleAction::Wipe)),
+            status_format: self
+                .status_format
+                .or_else(|| Some(DEFAULT_STATUS_FORMAT.to_string())),
+            reading_wpm: self.reading_wpm.or(Some(200)),
+            status_timeout_secs: self.status_timeout_secs.or(Some(3.0)),
+            ..self
+        }
+    }
+
+    /// Renders the config as TOML. TOML has no null, so a disabled `ttl`
+    /// or `idle` is written as a comment rather than left out silently.
+    pub fn to_toml(&self) -> Result<String, String> {
+        let mut out = String::new();
+        for (name, value) in [("ttl", self.ttl), ("idle", self.idle)] {
+            if value.is_none() {
+                out.push_str(&format!("# {} = off\n", name));
+            }
+        }
+        out.push_str(&toml::to_string(self).map_err(|e| e.to_string())?);
+        Ok(out)
+    }
+
+    /// Drops values that make no sense, returning a warning for each.
+    /// A `ttl` or `idle` of 0 means disabled; negative or non-finite values
+    /// are ignored rather than wiping the session the moment it starts.
+    pub fn validate(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        for (name, value) in [
+            ("ttl", &mut self.ttl),
+            ("idle", &mut self.idle),
+            ("key_rotation", &mut self.key_rotation),
+            ("ttl_extension", &mut self.ttl_extension),
+            ("autosave_interval", &mut self.autosave_interval),
+            ("status_timeout_secs", &mut self.status_timeout_secs),
+        ] {
+            validate_seconds(name, value, &mut warnings);
+        }
+
+        if let Some(width) = self.tab_width.filter(|&w| w == 0) {
+            warnings.push(format!("Ignoring invalid tab_width = {}", width));
+            self.tab_width = None;
+        }
+        if self.ruler_column == Some(0) {
+            warnings.push("Ignoring invalid ruler_column = 0".to_string());
+            self.ruler_column = None;
+        }
+        if self.max_buffer_bytes == Some(0) {
+            warnings.push("Ignoring invalid max_buffer_bytes = 0".to_string());
+            self.max_buffer_bytes = None;
+        }
+        if self.reading_wpm == Some(0) {
+            warnings.push("Ignoring invalid reading_wpm = 0".to_string());
+            self.reading_wpm = None;
+        }
+        if let Some(score) = self.min_password_score.filter(|&s| s > 4) {
+            warnings.push(format!(
+                "Ignoring invalid min_password_score = {} (must be 0-4)",
+                score
+            ));
+            self.min_password_score = None;
+        }
+
+        if let Some(theme) = &mut self.theme {
+            for (name, value) in theme.entries() {
+                if let Some(color) = value.as_deref().filter(|c| c.parse::<Color>().is_err()) {
+                    warnings.push(format!("Ignoring invalid theme.{} = {:?}", name, color));
+                    *value = None;
+                }
+            }
+        }
+
+        if let Some(spec) = self
+            .panic_key
+            .as_deref()
+            .filter(|s| KeySpec::parse(s).is_none())
+        {
+            warnings.push(format!("Ignoring invalid panic_key = {:?}", spec));
+            self.panic_key = None;
+        }
+        if let Some(bindings) = &mut self.keys {
+            bindings.retain(|name, spec| {
+                let problem = match (Action::from_name(name), KeySpec::parse(spec)) {
+                    (None, _) => Some("unknown action".to_string()),
+                    (_, None) => Some("unrecognized key".to_string()),
+                    (_, Some(key)) => keys::unbindable_reason(&key).map(str::to_string),
+                };
+                if let Some(problem) = &problem {
+                    warnings.push(format!("Ignoring keys.{} = {:?} ({})", name, spec, problem));
+                }
+                problem.is_none()
+            });
+        }
+        let bindings = self.key_bindings();
+        for (a, b) in bindings.conflicts() {
+            warnings.push(format!(
+                "keys.{} and keys.{} are both bound to {}; only {} will work",
+                a.name(),
+                b.name(),
+                bindings.key(a),
+                a.name()
+            ));
+        }
+
+        warnings
+    }
+
+    /// The default key bindings with `[keys]` and `panic_key` applied.
+    /// Entries that don't parse are skipped (`validate` warns about them).
+    pub fn key_bindings(&self) -> KeyBindings {
+        let mut bindings = KeyBindings::default();
+        let overrides = self.keys.iter().flatten();
+        for (name, spec) in overrides {
+            if let (Some(action), Some(key)) = (Action::from_name(name), KeySpec::parse(spec)) {
+                if keys::unbindable_reason(&key).is_none() {
+                    bindings.set(action, key);
+                }
+            }
+        }
+        if let Some(key) = self.panic_key.as_deref().and_then(KeySpec::parse) {
+            bindings.set(Action::PanicWipe, key);
+        }
+        bindings
+    }
+
+    /// Overrides `ttl` and `idle` from the `AMNESIA_TTL` and `AMNESIA_IDLE`
+    /// environment variables, looked up through `var`. CLI flags still win
+    /// over these. Returns a warning for each value that can't be used.
+    /// The rest of the config was validated when it loaded, so only these
+    /// two are checked again.
+    pub fn apply_env<F: Fn(&str) -> Option<String>>(&mut self, var: F) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for (name, value) in [
+            ("AMNESIA_TTL", &mut self.ttl),
+            ("AMNESIA_IDLE", &mut self.idle),
+        ] {
+            if let Some(raw) = var(name) {
+                match raw.trim().parse::<f64>() {
+                    Ok(v) => *value = Some(v),
+                    Err(_) => warnings.push(format!("Ignoring unparseable {}={:?}", name, raw)),
+                }
+            }
+        }
+        validate_seconds("ttl", &mut self.ttl, &mut warnings);
+        validate_seconds("idle", &mut self.idle, &mut warnings);
+        warnings
+    }
+
+    /// Validates, printing any warnings.
+    fn validated(mut self) -> Self {
+        for warning in self.validate() {
+            eprintln!("Warning: {}", warning);
+        }
+        self
+    }
+
+    /// Parses a config file, applying `[profiles.<profile>]` over the
+    /// top-level values if a profile is given. Tables such as `[theme]` are
+    /// merged key by key, so a profile only needs the entries it changes.
+    pub fn parse(content: &str, profile: Option<&str>) -> Result<Self, String> {
+        let mut table: toml::Table = toml::from_str(content).map_err(|e| e.to_string())?;
+        let profiles = table.remove("profiles");
+        if let Some(name) = profile {
+            let overrides = profiles
+                .as_ref()
+                .and_then(|p| p.get(name))
+                .and_then(toml::Value::as_table)
+                .ok_or_else(|| format!("No [profiles.{}] table", name))?;
+            merge_table(&mut table, overrides);
+        }
+        table.try_into().map_err(|e: toml::de::Error| e.to_string())
+    }
+
+    /// Loads the config from an explicit path. Unlike `load`, a missing or
+    /// unparseable file is an error rather than a fallback to defaults.
+    pub fn load_from<P: AsRef<Path>>(path: P, profile: Option<&str>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {:?}: {}", path, e))?;
+        Self::parse(&content, profile)
+            .map(Config::validated)
+            .map_err(|e| format!("Failed to parse config file {:?}: {}", path, e))
+    }
+
+    /// Where `load` looks for the config file, if the platform has a
+    /// config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        ProjectDirs::from("com", "laticee", "amnesia")
+            .map(|dirs| dirs.config_dir().join("config.toml"))
+    }
+
+    /// Loads the config from the default location. A missing file means
+    /// defaults; nothing is written to disk (see `write_default`).
+    /// Problems are only errors when a profile was asked for, as then the
+    /// defaults aren't what the user wanted.
+    pub fn load(profile: Option<&str>) -> Result<Self, String> {
+        let config_path = Self::default_path();
+        let content = config_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok());
+        match (content, profile) {
+            (Some(content), _) => match Self::parse(&content, profile) {
+                Ok(config) => Ok(config.validated()),
+                Err(e) if profile.is_some() => Err(format!(
+                    "Failed to load config file {:?}: {}",
+                    config_path.unwrap_or_default(),
+                    e
+                )),
+                Err(_) => {
+                    eprintln!(
+                        "Warning: Failed to parse config file at {:?}. Using defaults.",
+                        config_path.unwrap_or_default()
+                    );
+                    Ok(Self::default())
                 }
-            } else {
-                // Optionally create a default config file if it doesn't exist
-                let _ = fs::create_dir_all(config_dir);
-                let config_toml = r#"# amnesia configuration file (v1.1)
+            },
+            (None, Some(name)) => Err(format!(
+                "No config file, so no profile {:?} (see amnesia init-config)",
+                name
+            )),
+            (None, None) => Ok(Self::default()),
+        }
+    }
+
+    /// Writes the commented default config to `path`, creating its
+    /// directory. Fails rather than replace an existing file.
+    pub fn write_default<P: AsRef<Path>>(path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?
+            .write_all(DEFAULT_CONFIG.as_bytes())
+    }
+}
+
+/// The commented config file written by `amnesia init-config`.
+pub const DEFAULT_CONFIG: &str = r##"# amnesia configuration file (v1.1)
 
 # [ttl]
 # Time to live in minutes.
@@ -47,22 +469,233 @@ impl Config {
 # Use 0.0/none or comment out to disable.
 # ttl = 100.0
 
+# [ttl_extension]
+# Minutes Ctrl+T adds to the TTL. Default is the TTL itself.
+# ttl_extension = 30.0
+
+# [max_ttl_extensions]
+# How many times Ctrl+T may extend the TTL. Unlimited by default.
+# max_ttl_extensions = 3
+
 # [idle]
 # Idle timeout in seconds.
 # The application will exit if no input is received for this duration.
 # Default is 300.0 (5 minutes).
 idle = 300.0
 
+# [on_idle]
+# What to do when the idle timeout runs out: "wipe" memory and exit, or
+# "lock" the screen until an unlock password (asked for at startup) is
+# entered. Locking keeps the note encrypted in RAM. Default is "wipe".
+# on_idle = "wipe"
+
 # [stealth_encryption]
 # Enable stealth memory encryption (volatile-only).
 # Encrypts the RAM buffer with a key derived from system state and ASLR.
 # Note: Data is only accessible during the current session.
 # Default is false.
 stealth_encryption = false
-"#;
-                let _ = fs::write(config_path, config_toml);
+
+# [key_rotation]
+# With stealth encryption, replace the in-memory key with a fresh one
+# every this many seconds. Disabled by default.
+# key_rotation = 60.0
+
+# [strict_memory]
+# Refuse to run if the note can't be locked in RAM (mlock failed, e.g.
+# because of a low RLIMIT_MEMLOCK), instead of warning and continuing.
+# Default is false.
+# strict_memory = false
+
+# [warn_on_swap]
+# Show a warning in the status bar when the system has active swap and
+# memory couldn't be locked in RAM. Default is true.
+# warn_on_swap = true
+
+# [panic_key]
+# Key that instantly wipes memory and exits without a goodbye message.
+# Default is "ctrl+\\".
+# panic_key = "ctrl+\\"
+
+# [max_load_attempts]
+# Securely overwrite and delete a file after this many wrong passwords
+# in the Open (Ctrl+O) popup. Disabled by default.
+# max_load_attempts = 5
+
+# [tab_width]
+# Number of columns a tab character is displayed as. Default is 4.
+# tab_width = 4
+
+# [max_buffer_bytes]
+# Refuse typing, pastes and piped input that would grow the note past
+# this many bytes, so the pinned allocation can't outgrow RLIMIT_MEMLOCK.
+# Unlimited by default.
+# max_buffer_bytes = 1048576
+
+# [highlight_current_line]
+# Give the line with the cursor a subtle background (theme.current_line).
+# Default is false.
+# highlight_current_line = false
+
+# [ruler]
+# Draw a dim vertical line at ruler_column to help keep lines short.
+# Default is false.
+# ruler = false
+
+# [ruler_column]
+# Column the ruler is drawn at. Default is 80.
+# ruler_column = 80
+
+# [wrap]
+# Soft-wrap long lines at the window width instead of scrolling sideways.
+# Toggle at runtime with Alt+Z. Default is false.
+# wrap = false
+
+# [show_whitespace]
+# Draw spaces as · and tabs as →, with trailing whitespace in red.
+# Toggle at runtime with Alt+W. Default is false.
+# show_whitespace = false
+
+# [line_numbers]
+# Line-number gutter: "off", "absolute" or "relative" (the cursor's line
+# shows its number, the others their distance from it). Cycle at runtime
+# with Alt+N. Default is "off".
+# line_numbers = "off"
+
+# [redact]
+# Start with the note masked as •••, for shoulder-surfing protection.
+# Toggle at runtime with Alt+R; editing still works while masked.
+# Default is false.
+# redact = false
+
+# [spell_check]
+# Underline words that aren't in the dictionary. Needs a build with the
+# "spellcheck" feature. Toggle at runtime with F7. Default is false.
+# spell_check = false
+
+# [dictionary]
+# Word list for spell checking, one word per line. Checked locally.
+# Default is "/usr/share/dict/words".
+# dictionary = "/usr/share/dict/words"
+
+# [read_only_output]
+# Mark saved .amnesio files read-only. Disable to allow repeated saves
+# to the same path. Default is true.
+# read_only_output = true
+
+# [scrub_on_overwrite]
+# When saving over an existing file, overwrite the old file's bytes with
+# random data once the new one is in place, so the previous ciphertext
+# doesn't linger on disk. Slower for large files. Default is false.
+# scrub_on_overwrite = false
+
+# [line_ending]
+# Line endings written on save: "lf" or "crlf". Text is always edited
+# with LF; CRLF in pasted, piped or opened text is converted on the way
+# in. Default is "lf".
+# line_ending = "lf"
+
+# [autosave]
+# Periodically save the note, encrypted, to this file so a crash or an
+# expired TTL doesn't lose it. Asks for a password for it at startup.
+# Each auto-save scrubs the previous one. The file is left on disk when
+# the session ends. Disabled by default.
+# autosave = "/path/to/autosave.amnesio"
+
+# [autosave_interval]
+# Seconds between auto-saves; unchanged notes aren't rewritten.
+# Default is 60.
+# autosave_interval = 60.0
+
+# [min_password_score]
+# Refuse to save with a password whose estimated strength (0-4, shown
+# in the save popup) is below this. Disabled by default.
+# min_password_score = 3
+
+# [status_format]
+# Status bar template. Tokens: {stealth} {line} {col} {idle} {idle_max}
+# {ttl} {words} {read} (reading time, empty for an empty note) {mem}
+# (buffer bytes used / allocated). Unknown tokens are shown as written.
+# status_format = " {stealth}{line}:{col} | {mem} | Idle: {idle}/{idle_max}s | TTL: {ttl}"
+
+# [reading_wpm]
+# Reading speed behind the {read} status token. Default is 200.
+# reading_wpm = 200
+
+# [status_timeout_secs]
+# How long messages such as "Saved" or errors stay in the status bar.
+# Default is 3.
+# status_timeout_secs = 3.0
+
+# Colors, as names ("lightblue"), hex ("#1e1e2e") or 256-color indices
+# ("42"). Unset entries keep the defaults shown here. Tables must come
+# after all other settings.
+# [theme]
+# editor_fg = "white"
+# editor_bg = "black"
+# status_fg = "black"
+# status_bg = "darkgray"
+# border = "darkgray"
+# popup_border = "cyan"
+# read_only = "red"
+# stealth = "black"
+# current_line = "236"
+
+# Remap actions to other keys, e.g. "ctrl+q", "alt+s" or "f2". Plain
+# letters and editing keys can't be bound. Esc always closes popups.
+# Actions: help save open insert_file new_tab next_tab tab_1 ... tab_9
+# close_tab clear markdown split_preview wrap whitespace line_numbers redact
+# spell_check goto_line delete_word select_all top bottom copy extend_ttl
+# show_password random_filename panic quit
+# [keys]
+# save = "ctrl+s"
+# quit = "esc"
+
+# [profiles]
+# Named sets of overrides, picked with --profile <name>. Any option above
+# can go in a profile; the ones it leaves out keep their top-level values.
+# [profiles.work]
+# ttl = 30.0
+# idle = 60.0
+#
+# [profiles.journal]
+# idle = 1800.0
+# [profiles.journal.theme]
+# editor_bg = "#1e1e2e"
+"##;
+
+/// Clears a duration that is 0 (disabled), or negative or non-finite, which
+/// also gets a warning.
+fn validate_seconds(name: &str, value: &mut Option<f64>, warnings: &mut Vec<String>) {
+    match *value {
+        Some(v) if !v.is_finite() || v < 0.0 => {
+            warnings.push(format!("Ignoring invalid {} = {}", name, v));
+            *value = None;
+        }
+        Some(0.0) => *value = None,
+        _ => {}
+    }
+}
+
+/// Overlays `overrides` on `base`, recursing into tables both have.
+fn merge_table(base: &mut toml::Table, overrides: &toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => merge_table(base, value),
+            _ => {
+                base.insert(key.clone(), value.clone());
             }
         }
-        Self::default()
     }
 }
+
+/// Minutes from `now` until the next occurrence of the wall-clock time
+/// `spec` (`HH:MM`). A time that has already passed today means tomorrow.
+pub fn minutes_until(spec: &str, now: NaiveTime) -> Option<f64> {
+    let target = NaiveTime::parse_from_str(spec.trim(), "%H:%M").ok()?;
+    let mut secs = (target - now).num_seconds();
+    if secs <= 0 {
+        secs += 24 * 60 * 60;
+    }
+    Some(secs as f64 / 60.0)
+}
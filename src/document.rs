@@ -0,0 +1,107 @@
+use crate::mem_buffer::MemoryBuffer;
+use crate::persistence::{self, PersistenceError, SaveInfo};
+use crate::stealth;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use zeroize::Zeroize;
+
+/// Room allocated up front, the same as the editor's.
+const INITIAL_CAPACITY: usize = 64 * 1024;
+
+/// A note held in pinned memory that is zeroed on drop, without the TUI.
+/// Offsets are bytes; ones inside a character move back to its start.
+/// With `stealth`, the text is also encrypted in RAM under a per-document
+/// key from `stealth::derive_key`.
+pub struct SecureDocument {
+    buffer: MemoryBuffer,
+}
+
+impl SecureDocument {
+    /// Creates an empty document. Fails if the memory can't be locked in
+    /// RAM, e.g. because `RLIMIT_MEMLOCK` is too low.
+    pub fn new(stealth: bool) -> io::Result<Self> {
+        let mut key = stealth.then(stealth::derive_key);
+        let buffer = MemoryBuffer::new(INITIAL_CAPACITY, key);
+        key.zeroize();
+        Ok(Self { buffer: buffer? })
+    }
+
+    /// Like `new`, but keeps going if the memory can't be locked.
+    /// Check `is_locked` to find out.
+    pub fn new_lenient(stealth: bool) -> Self {
+        let mut key = stealth.then(stealth::derive_key);
+        let buffer = MemoryBuffer::new_lenient(INITIAL_CAPACITY, key);
+        key.zeroize();
+        Self { buffer }
+    }
+
+    /// Opens a password-protected .amnesio file into a new document.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        password: &str,
+        stealth: bool,
+    ) -> Result<Self, PersistenceError> {
+        let mut content = persistence::load_encrypted(path, password)?;
+        let mut document = Self::new_lenient(stealth);
+        document.buffer.update(&content);
+        content.zeroize();
+        Ok(document)
+    }
+
+    /// Encrypts the document with `password` and writes it to `path`.
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+        password: &str,
+    ) -> Result<SaveInfo, PersistenceError> {
+        self.with_text(|text| persistence::save_encrypted(path, text, password, true))
+    }
+
+    /// Inserts `text` at byte offset `at`.
+    pub fn insert(&mut self, at: usize, text: &str) {
+        self.buffer.insert_str_at(at, text);
+    }
+
+    /// Deletes the bytes in `range`.
+    pub fn delete(&mut self, range: Range<usize>) {
+        self.buffer.remove_range(range);
+    }
+
+    /// Replaces the whole text.
+    pub fn set_text(&mut self, text: &str) {
+        self.buffer.update(text);
+    }
+
+    /// Runs `f` over a plaintext copy of the text, zeroing it afterwards.
+    pub fn with_text<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+        let mut text = self.buffer.to_string();
+        let result = f(&text);
+        text.zeroize();
+        result
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.buffer.byte_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.byte_len() == 0
+    }
+
+    /// A counter that changes whenever the text does.
+    pub fn generation(&self) -> u64 {
+        self.buffer.generation()
+    }
+
+    /// Returns true if the whole document is pinned in RAM.
+    pub fn is_locked(&self) -> bool {
+        self.buffer.is_locked()
+    }
+
+    /// Zeroes the text immediately, leaving the document empty.
+    pub fn wipe(&mut self) {
+        self.buffer.wipe();
+    }
+}
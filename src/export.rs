@@ -0,0 +1,33 @@
+use pulldown_cmark::{html, Options, Parser};
+use std::io::{self, Write};
+
+/// Writes `markdown` as a standalone HTML document titled `title`.
+/// The HTML is streamed straight into `out`, so no rendered copy of the
+/// note is built up in memory.
+pub fn write_html<W: Write>(out: &mut W, markdown: &str, title: &str) -> io::Result<()> {
+    write!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n",
+        escape(title)
+    )?;
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    html::write_html_io(&mut *out, Parser::new_ext(markdown, options))?;
+    out.write_all(b"</body>\n</html>\n")
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,418 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Something a key can be bound to. Plain text editing (typing, arrows,
+/// Backspace, Enter) isn't remappable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Help,
+    Save,
+    Open,
+    InsertFile,
+    NewTab,
+    NextTab,
+    GotoTab1,
+    GotoTab2,
+    GotoTab3,
+    GotoTab4,
+    GotoTab5,
+    GotoTab6,
+    GotoTab7,
+    GotoTab8,
+    GotoTab9,
+    CloseTab,
+    ClearNote,
+    MarkdownToggle,
+    SplitPreview,
+    WrapToggle,
+    WhitespaceToggle,
+    LineNumbers,
+    Redact,
+    SpellCheckToggle,
+    GotoLine,
+    DeleteWordBackward,
+    SelectAll,
+    Top,
+    Bottom,
+    CopyClipboard,
+    ExtendTtl,
+    TogglePasswordVisibility,
+    RandomFilename,
+    PanicWipe,
+    Quit,
+}
+
+impl Action {
+    /// Every action, in the order the F1 help lists them.
+    pub const ALL: [Action; 35] = [
+        Action::Help,
+        Action::Save,
+        Action::Open,
+        Action::InsertFile,
+        Action::NewTab,
+        Action::NextTab,
+        Action::GotoTab1,
+        Action::GotoTab2,
+        Action::GotoTab3,
+        Action::GotoTab4,
+        Action::GotoTab5,
+        Action::GotoTab6,
+        Action::GotoTab7,
+        Action::GotoTab8,
+        Action::GotoTab9,
+        Action::CloseTab,
+        Action::ClearNote,
+        Action::MarkdownToggle,
+        Action::SplitPreview,
+        Action::WrapToggle,
+        Action::WhitespaceToggle,
+        Action::LineNumbers,
+        Action::Redact,
+        Action::SpellCheckToggle,
+        Action::GotoLine,
+        Action::DeleteWordBackward,
+        Action::SelectAll,
+        Action::Top,
+        Action::Bottom,
+        Action::CopyClipboard,
+        Action::ExtendTtl,
+        Action::TogglePasswordVisibility,
+        Action::RandomFilename,
+        Action::PanicWipe,
+        Action::Quit,
+    ];
+
+    /// The name used in the `[keys]` config table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Help => "help",
+            Action::Save => "save",
+            Action::Open => "open",
+            Action::InsertFile => "insert_file",
+            Action::NewTab => "new_tab",
+            Action::NextTab => "next_tab",
+            Action::GotoTab1 => "tab_1",
+            Action::GotoTab2 => "tab_2",
+            Action::GotoTab3 => "tab_3",
+            Action::GotoTab4 => "tab_4",
+            Action::GotoTab5 => "tab_5",
+            Action::GotoTab6 => "tab_6",
+            Action::GotoTab7 => "tab_7",
+            Action::GotoTab8 => "tab_8",
+            Action::GotoTab9 => "tab_9",
+            Action::CloseTab => "close_tab",
+            Action::ClearNote => "clear",
+            Action::MarkdownToggle => "markdown",
+            Action::SplitPreview => "split_preview",
+            Action::WrapToggle => "wrap",
+            Action::WhitespaceToggle => "whitespace",
+            Action::LineNumbers => "line_numbers",
+            Action::Redact => "redact",
+            Action::SpellCheckToggle => "spell_check",
+            Action::GotoLine => "goto_line",
+            Action::DeleteWordBackward => "delete_word",
+            Action::SelectAll => "select_all",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::CopyClipboard => "copy",
+            Action::ExtendTtl => "extend_ttl",
+            Action::TogglePasswordVisibility => "show_password",
+            Action::RandomFilename => "random_filename",
+            Action::PanicWipe => "panic",
+            Action::Quit => "quit",
+        }
+    }
+
+    /// The go-to-tab actions, first tab first.
+    pub const GOTO_TAB: [Action; 9] = [
+        Action::GotoTab1,
+        Action::GotoTab2,
+        Action::GotoTab3,
+        Action::GotoTab4,
+        Action::GotoTab5,
+        Action::GotoTab6,
+        Action::GotoTab7,
+        Action::GotoTab8,
+        Action::GotoTab9,
+    ];
+
+    /// The tab a go-to-tab action switches to, counting from 0.
+    pub fn tab_index(self) -> Option<usize> {
+        Self::GOTO_TAB.iter().position(|&a| a == self)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Help => "Show / hide this help",
+            Action::Save => "Save encrypted",
+            Action::Open => "Open encrypted",
+            Action::InsertFile => "Insert a plaintext file at the cursor",
+            Action::NewTab => "New tab",
+            Action::NextTab => "Next tab",
+            Action::GotoTab1 => "Go to tab 1",
+            Action::GotoTab2 => "Go to tab 2",
+            Action::GotoTab3 => "Go to tab 3",
+            Action::GotoTab4 => "Go to tab 4",
+            Action::GotoTab5 => "Go to tab 5",
+            Action::GotoTab6 => "Go to tab 6",
+            Action::GotoTab7 => "Go to tab 7",
+            Action::GotoTab8 => "Go to tab 8",
+            Action::GotoTab9 => "Go to tab 9",
+            Action::CloseTab => "Close tab, wiping its note (press twice)",
+            Action::ClearNote => "Clear the note (asks if unsaved)",
+            Action::MarkdownToggle => "Toggle Markdown preview",
+            Action::SplitPreview => "Toggle side-by-side Markdown preview",
+            Action::WrapToggle => "Toggle line wrap",
+            Action::WhitespaceToggle => "Show / hide whitespace",
+            Action::LineNumbers => "Line numbers: off / absolute / relative",
+            Action::Redact => "Mask / reveal the note (privacy screen)",
+            Action::SpellCheckToggle => "Toggle spell check",
+            Action::GotoLine => "Go to line",
+            Action::DeleteWordBackward => "Delete the previous word",
+            Action::SelectAll => "Select all (typing or Backspace replaces it)",
+            Action::Top => "Jump to top",
+            Action::Bottom => "Jump to bottom",
+            Action::CopyClipboard => "Copy all to terminal clipboard (press twice)",
+            Action::ExtendTtl => "Extend TTL",
+            Action::TogglePasswordVisibility => "Show / hide password (password popups)",
+            Action::RandomFilename => "Random file name (save popup)",
+            Action::PanicWipe => "Panic wipe",
+            Action::Quit => "Exit (Esc always closes popups)",
+        }
+    }
+
+    fn default_key(self) -> KeySpec {
+        let ctrl = KeyModifiers::CONTROL;
+        let alt = KeyModifiers::ALT;
+        match self {
+            Action::Help => KeySpec::new(KeyCode::F(1), KeyModifiers::NONE),
+            Action::Save => KeySpec::new(KeyCode::Char('s'), ctrl),
+            Action::Open => KeySpec::new(KeyCode::Char('o'), ctrl),
+            Action::InsertFile => KeySpec::new(KeyCode::Char('i'), alt),
+            Action::NewTab => KeySpec::new(KeyCode::Char('t'), alt),
+            Action::NextTab => KeySpec::new(KeyCode::Tab, ctrl),
+            Action::GotoTab1 => KeySpec::new(KeyCode::Char('1'), alt),
+            Action::GotoTab2 => KeySpec::new(KeyCode::Char('2'), alt),
+            Action::GotoTab3 => KeySpec::new(KeyCode::Char('3'), alt),
+            Action::GotoTab4 => KeySpec::new(KeyCode::Char('4'), alt),
+            Action::GotoTab5 => KeySpec::new(KeyCode::Char('5'), alt),
+            Action::GotoTab6 => KeySpec::new(KeyCode::Char('6'), alt),
+            Action::GotoTab7 => KeySpec::new(KeyCode::Char('7'), alt),
+            Action::GotoTab8 => KeySpec::new(KeyCode::Char('8'), alt),
+            Action::GotoTab9 => KeySpec::new(KeyCode::Char('9'), alt),
+            Action::CloseTab => KeySpec::new(KeyCode::Char('x'), alt),
+            Action::ClearNote => KeySpec::new(KeyCode::Char('n'), ctrl),
+            Action::MarkdownToggle => KeySpec::new(KeyCode::Char('p'), ctrl),
+            Action::SplitPreview => KeySpec::new(KeyCode::Char('p'), alt),
+            Action::WrapToggle => KeySpec::new(KeyCode::Char('z'), alt),
+            Action::WhitespaceToggle => KeySpec::new(KeyCode::Char('w'), alt),
+            Action::LineNumbers => KeySpec::new(KeyCode::Char('n'), alt),
+            Action::Redact => KeySpec::new(KeyCode::Char('r'), alt),
+            Action::SpellCheckToggle => KeySpec::new(KeyCode::F(7), KeyModifiers::NONE),
+            Action::GotoLine => KeySpec::new(KeyCode::Char('g'), ctrl),
+            Action::DeleteWordBackward => KeySpec::new(KeyCode::Char('w'), ctrl),
+            Action::SelectAll => KeySpec::new(KeyCode::Char('a'), ctrl),
+            Action::Top => KeySpec::new(KeyCode::Home, ctrl),
+            Action::Bottom => KeySpec::new(KeyCode::End, ctrl),
+            Action::CopyClipboard => KeySpec::new(KeyCode::Char('c'), alt),
+            Action::ExtendTtl => KeySpec::new(KeyCode::Char('t'), ctrl),
+            Action::TogglePasswordVisibility => KeySpec::new(KeyCode::Char('h'), ctrl),
+            Action::RandomFilename => KeySpec::new(KeyCode::Char('r'), ctrl),
+            Action::PanicWipe => KeySpec::new(KeyCode::Char('\\'), ctrl),
+            Action::Quit => KeySpec::new(KeyCode::Esc, KeyModifiers::NONE),
+        }
+    }
+}
+
+/// The key bound to each action. Defaults match the built-in bindings;
+/// the `[keys]` config table overrides them by action name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    keys: [KeySpec; Action::ALL.len()],
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: Action::ALL.map(Action::default_key),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeySpec {
+        self.keys[action as usize]
+    }
+
+    pub fn set(&mut self, action: Action, key: KeySpec) {
+        self.keys[action as usize] = key;
+    }
+
+    /// The action bound to this key event, if any.
+    pub fn action_for(&self, event: &KeyEvent) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|&action| self.key(action).matches(event))
+    }
+
+    /// `Alt+1..9` when the go-to-tab keys are one modifier plus 1 to 9 in
+    /// order, so the help can show them on one line.
+    pub fn goto_tab_range(&self) -> Option<String> {
+        let first = self.key(Action::GOTO_TAB[0]);
+        let in_order = Action::GOTO_TAB
+            .into_iter()
+            .zip('1'..='9')
+            .all(|(action, digit)| {
+                self.key(action) == KeySpec::new(KeyCode::Char(digit), first.modifiers)
+            });
+        in_order.then(|| format!("{}..9", first))
+    }
+
+    /// Pairs of actions bound to the same key. Only the first of each pair
+    /// is reachable.
+    pub fn conflicts(&self) -> Vec<(Action, Action)> {
+        let mut conflicts = Vec::new();
+        for (i, &a) in Action::ALL.iter().enumerate() {
+            for &b in &Action::ALL[i + 1..] {
+                if self.key(a) == self.key(b) {
+                    conflicts.push((a, b));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+/// Returns why `key` can't be bound to an action, if it can't.
+pub fn unbindable_reason(key: &KeySpec) -> Option<&'static str> {
+    let plain = key.modifiers.difference(KeyModifiers::SHIFT).is_empty();
+    match key.code {
+        KeyCode::Char(_) if plain => Some("it would block typing"),
+        KeyCode::Enter
+        | KeyCode::Backspace
+        | KeyCode::Left
+        | KeyCode::Right
+        | KeyCode::Up
+        | KeyCode::Down
+            if plain =>
+        {
+            Some("it is used for editing")
+        }
+        _ => None,
+    }
+}
+
+/// A single key chord such as `ctrl+s` or `f1`, parsed from config strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpec {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeySpec {
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// Parses strings like `"ctrl+\\"`, `"alt+backspace"` or `"f1"`.
+    /// Modifier and key names are case-insensitive.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let parts: Vec<&str> = spec.trim().split('+').collect();
+        // A trailing "+" means the key itself is '+', e.g. "ctrl++".
+        let (key, mods) = if spec.ends_with("++") {
+            ("+", &parts[..parts.len() - 2])
+        } else {
+            let (key, mods) = parts.split_last()?;
+            (*key, mods)
+        };
+
+        for m in mods {
+            match m.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+                "alt" | "meta" => modifiers |= KeyModifiers::ALT,
+                "shift" => modifiers |= KeyModifiers::SHIFT,
+                _ => return None,
+            }
+        }
+
+        let code = match key.to_ascii_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "backspace" => KeyCode::Backspace,
+            "tab" => KeyCode::Tab,
+            "delete" | "del" => KeyCode::Delete,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            k if k.len() > 1 && k.starts_with('f') => KeyCode::F(k[1..].parse().ok()?),
+            _ => {
+                let mut chars = key.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+        };
+
+        Some(Self { code, modifiers })
+    }
+
+    /// Returns true if the key event is this chord.
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        if event.modifiers != self.modifiers {
+            return false;
+        }
+        match (self.code, event.code) {
+            (KeyCode::Char(a), KeyCode::Char(b)) => {
+                let b = b.to_ascii_lowercase();
+                // Legacy terminals report Ctrl+\ ] ^ _ as Ctrl+4..7.
+                a == b || (self.modifiers.contains(KeyModifiers::CONTROL) && legacy_ctrl(b) == a)
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl std::fmt::Display for KeySpec {
+    /// Formats as `Ctrl+S`, `Alt+Backspace` or `F1`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::PageUp => f.write_str("PageUp"),
+            KeyCode::PageDown => f.write_str("PageDown"),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+fn legacy_ctrl(c: char) -> char {
+    match c {
+        '4' => '\\',
+        '5' => ']',
+        '6' => '^',
+        '7' => '_',
+        other => other,
+    }
+}
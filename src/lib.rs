@@ -1,5 +1,17 @@
+//! The secure buffer, encrypted file format and TUI editor behind the
+//! `amnesia` binary. `SecureDocument` and `persistence` work without the TUI.
+
 pub mod config;
+pub mod document;
+pub mod export;
+pub mod keys;
 pub mod mem_buffer;
 pub mod persistence;
+pub mod secret;
+#[cfg(feature = "spellcheck")]
+pub mod spell;
 pub mod stealth;
 pub mod tui_app;
+
+pub use document::SecureDocument;
+pub use mem_buffer::MemoryBuffer;
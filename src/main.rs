@@ -1,15 +1,25 @@
-use amnesia::config::Config;
+mod commands;
+
+use amnesia::config::{self, Config, IdleAction, LineEnding};
+use amnesia::keys::Action;
+use amnesia::persistence::{self, Credential, KeySource};
 use amnesia::stealth;
-use amnesia::tui_app::Editor;
-use clap::Parser;
+use amnesia::tui_app::{Editor, InputMode};
+use clap::{CommandFactory, Parser, Subcommand};
 use crossterm::{
-    event::{self, Event, KeyCode},
+    cursor::MoveTo,
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
-use std::io;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use zeroize::Zeroize;
 
@@ -22,8 +32,11 @@ use std::path::PathBuf;
     version,
     about = "amnesia: A volatile-only, privacy-focused CLI notepad."
 )]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
-    /// Optional file to load (read-only)
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Optional file to load (read-only)
     #[arg()]
     file: Option<PathBuf>,
@@ -32,6 +45,11 @@ struct Args {
     #[arg(long)]
     ttl: Option<f64>,
 
+    /// Self-destruct at the next occurrence of this local time (HH:MM).
+    /// With --ttl as well, whichever comes first wins
+    #[arg(long, value_name = "HH:MM")]
+    ttl_at: Option<String>,
+
     /// Idle timeout in seconds
     #[arg(long)]
     idle: Option<f64>,
@@ -39,24 +57,182 @@ struct Args {
     /// Enable stealth memory encryption (volatile-only)
     #[arg(long)]
     encrypt: bool,
+
+    /// Open in read-only mode (always on when loading a file)
+    #[arg(long)]
+    read_only: bool,
+
+    /// Load settings from this config file instead of the default location
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Ignore the config file and AMNESIA_* variables; use only flags and defaults
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+
+    /// Apply this [profiles.<name>] table from the config file
+    #[arg(long, value_name = "NAME", conflicts_with = "no_config")]
+    profile: Option<String>,
+
+    /// Print the effective settings as TOML and exit
+    #[arg(long)]
+    print_config: bool,
+
+    /// Exit instead of running if memory can't be locked in RAM
+    #[arg(long)]
+    strict_memory: bool,
+
+    /// Derive file keys from this keyfile instead of asking for a password
+    #[arg(long, value_name = "PATH")]
+    keyfile: Option<PathBuf>,
+
+    /// Save to this age public key instead of asking for a password (repeatable)
+    #[arg(long = "recipient", value_name = "AGE1...")]
+    recipients: Vec<age::x25519::Recipient>,
+
+    /// Open files saved to age recipients with the private keys in this file
+    #[arg(long, value_name = "PATH")]
+    identity: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Encrypt plaintext from stdin into an .amnesio file
+    Encrypt {
+        /// Output file
+        output: PathBuf,
+
+        /// Read the password from the first line of stdin instead of prompting
+        #[arg(long)]
+        password_stdin: bool,
+    },
+
+    /// Change the password of an .amnesio file
+    Passwd {
+        /// File to re-encrypt
+        file: PathBuf,
+    },
+
+    /// Re-save an .amnesio file from an older format version in the current one
+    Migrate {
+        /// File to upgrade
+        file: PathBuf,
+    },
+
+    /// Check an .amnesio file's integrity and password without opening it
+    Verify {
+        /// File to check
+        file: PathBuf,
+    },
+
+    /// Show an .amnesio file's format, key type and cipher without opening it
+    Info {
+        /// File to inspect
+        file: PathBuf,
+    },
+
+    /// Render an .amnesio file's Markdown to an UNENCRYPTED HTML file
+    Export {
+        /// File to export
+        file: PathBuf,
+
+        /// HTML file to write
+        output: PathBuf,
+
+        /// Don't ask before writing plaintext to disk
+        #[arg(long)]
+        yes: bool,
+    },
+
+    /// Write a commented default config file (to the default location
+    /// unless a path is given)
+    InitConfig {
+        /// Where to write it
+        path: Option<PathBuf>,
+    },
+
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions {
+        /// Shell to generate completions for
+        shell: clap_complete::Shell,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
-    let config = Config::load();
 
-    // Determine values, prioritizing CLI args over config, then hardcoded defaults.
-    let ttl = args.ttl.or(config.ttl);
+    if let Some(command) = args.command {
+        return match command {
+            Command::Encrypt {
+                output,
+                password_stdin,
+            } => commands::encrypt(&output, password_stdin),
+            Command::Passwd { file } => commands::passwd(&file),
+            Command::Export { file, output, yes } => commands::export(&file, &output, yes),
+            Command::Migrate { file } => commands::migrate(&file),
+            Command::Verify { file } => commands::verify(&file),
+            Command::Info { file } => commands::info(&file),
+            Command::InitConfig { path } => commands::init_config(path),
+            Command::Completions { shell } => {
+                clap_complete::generate(shell, &mut Args::command(), "amnesia", &mut io::stdout());
+                Ok(())
+            }
+        };
+    }
+
+    let profile = args.profile.as_deref();
+    let loaded = match &args.config {
+        _ if args.no_config => Ok(Config::default()),
+        Some(path) => Config::load_from(path, profile),
+        None => Config::load(profile),
+    };
+    let mut config = loaded.unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    if !args.no_config {
+        for warning in config.apply_env(|name| std::env::var(name).ok()) {
+            eprintln!("Warning: {}", warning);
+        }
+    }
 
-    let idle_secs = match (args.idle, ttl) {
-        (Some(i), _) => Some(i),                       // Explicit --idle
-        (None, _) if args.idle.is_some() => None, // This shouldn't happen with Option but for clarity
-        (None, Some(_)) if args.ttl.is_some() => None, // --ttl provided via CLI, no --idle provided via CLI
-        (None, _) => args.idle.or(config.idle),        // Use config or default
+    // Determine values, prioritizing CLI args over environment, then config, then hardcoded defaults.
+    let ttl_at = args.ttl_at.as_deref().map(|spec| {
+        config::minutes_until(spec, chrono::Local::now().time()).unwrap_or_else(|| {
+            eprintln!("Error: Invalid --ttl-at {:?}, expected HH:MM.", spec);
+            std::process::exit(1);
+        })
+    });
+    // Both given on the CLI: the earlier deadline wins.
+    let cli_ttl = match (args.ttl, ttl_at) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
     };
+    let ttl = cli_ttl.or(config.ttl);
+
+    let idle_secs = config.resolve_idle(args.idle, cli_ttl);
+
+    let lock_on_idle =
+        config.on_idle.unwrap_or_default() == IdleAction::Lock && idle_secs.is_some();
+    // Locking relies on the note staying encrypted in RAM.
+    let use_encryption = args.encrypt || config.stealth_encryption.unwrap_or(false) || lock_on_idle;
+    let strict_memory = args.strict_memory || config.strict_memory.unwrap_or(false);
+
+    if args.print_config {
+        let effective = Config {
+            ttl,
+            idle: idle_secs,
+            stealth_encryption: Some(use_encryption),
+            strict_memory: Some(strict_memory),
+            ..config
+        }
+        .with_defaults();
+        print!("{}", effective.to_toml()?);
+        return Ok(());
+    }
 
-    let use_encryption = args.encrypt || config.stealth_encryption.unwrap_or(false);
     let encryption_key = if use_encryption {
         let key = stealth::derive_key();
         Some(key)
@@ -65,8 +241,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Check if we are loading a file (Read-Only mode)
-    let read_only = args.file.is_some();
+    let read_only = args.read_only || args.file.is_some();
     let mut initial_content = String::new();
+    let mut damaged_text = false;
+
+    if let Some(keyfile) = &args.keyfile {
+        if !keyfile.is_file() {
+            eprintln!("Error: Keyfile {:?} does not exist.", keyfile);
+            std::process::exit(1);
+        }
+    }
 
     if let Some(path) = &args.file {
         if !path.exists() {
@@ -75,19 +259,114 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
 
         println!("Loading encrypted file (Read-Only): {:?}", path);
-        let password = rpassword::prompt_password("Enter password: ")
-            .map_err(|e| format!("Failed to read password: {}", e))?;
-
-        initial_content = amnesia::persistence::load_encrypted(path, &password)
-            .map_err(|e| format!("Failed to load file: {}", e))?;
+        let source =
+            persistence::key_source(path).map_err(|e| format!("Failed to load file: {}", e))?;
+        let result = match (source, &args.keyfile) {
+            (KeySource::Keyfile, Some(keyfile)) => {
+                let mut bytes = persistence::read_keyfile(keyfile)
+                    .map_err(|e| format!("Failed to read keyfile: {}", e))?;
+                let result =
+                    persistence::load_with_credential_lossy(path, Credential::Keyfile(&bytes));
+                bytes.zeroize();
+                result
+            }
+            (KeySource::Recipients, _) => match &args.identity {
+                Some(identity) => persistence::load_with_identity_lossy(path, identity),
+                None => Err(persistence::PersistenceError::WrongKeySource(source)),
+            },
+            (KeySource::Keyfile, None) => {
+                Err(persistence::PersistenceError::WrongKeySource(source))
+            }
+            (KeySource::Password, _) => {
+                let mut password = rpassword::prompt_password("Enter password: ")
+                    .map_err(|e| format!("Failed to read password: {}", e))?;
+                let result =
+                    persistence::load_with_credential_lossy(path, Credential::Password(&password));
+                password.zeroize();
+                result
+            }
+        };
+        let (content, _, damaged) = result.map_err(|e| format!("Failed to load file: {}", e))?;
+        initial_content = content;
+        damaged_text = damaged;
 
-        println!("File loaded successfully. Press Enter to start Amnesia.");
+        if damaged {
+            eprintln!("{}", amnesia::tui_app::DAMAGED_TEXT_WARNING);
+        } else {
+            println!("File loaded successfully. Press Enter to start Amnesia.");
+        }
         // Optional: wait for Enter so user sees success message?
         // Or just proceed.
         // Given we are about to clear screen, maybe a small pause or just proceed is fine.
+    } else if !io::stdin().is_terminal() {
+        // Piped input (`cat notes.txt | amnesia`). Keyboard input then comes from /dev/tty.
+        let bytes = amnesia::secret::read_all_zeroizing(io::stdin().lock())
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+        initial_content = amnesia::secret::into_string_zeroizing(bytes);
+        if let Some(max) = config.max_buffer_bytes {
+            if initial_content.len() > max {
+                let len = initial_content.len();
+                initial_content.zeroize();
+                return Err(format!(
+                    "Piped input is {} bytes, over max_buffer_bytes = {}",
+                    len, max
+                )
+                .into());
+            }
+        }
     }
 
+    let mut unlock_password = if lock_on_idle {
+        println!("Idle lock is on. Choose a password to unlock the screen.");
+        Some(commands::prompt_new_password()?)
+    } else {
+        None
+    };
+
+    let mut autosave_password = if let Some(path) = &config.autosave {
+        println!("Auto-save is on. Choose a password for {:?}.", path);
+        let mut password = commands::prompt_new_password()?;
+        let problem = if password.len() < persistence::MIN_PASSWORD_LEN {
+            Some(format!(
+                "Password too short (min {} chars)",
+                persistence::MIN_PASSWORD_LEN
+            ))
+        } else if password.len() > amnesia::secret::SECRET_CAPACITY {
+            Some(format!(
+                "Password too long (max {} bytes)",
+                amnesia::secret::SECRET_CAPACITY
+            ))
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            password.zeroize();
+            return Err(problem.into());
+        }
+        Some(password)
+    } else {
+        None
+    };
+
+    #[cfg(feature = "spellcheck")]
+    let dictionary = {
+        let path = config
+            .dictionary
+            .as_deref()
+            .unwrap_or(amnesia::spell::DEFAULT_DICTIONARY);
+        match amnesia::spell::Dictionary::load(path) {
+            Ok(dictionary) => Some(dictionary),
+            Err(e) => {
+                if config.dictionary.is_some() || config.spell_check == Some(true) {
+                    eprintln!("Warning: Failed to load dictionary {:?}: {}", path, e);
+                }
+                None
+            }
+        }
+    };
+
     // 1. Disable core dumps to prevent RAM data from being written to disk on crash.
+    #[cfg(unix)]
     unsafe {
         let limit = libc::rlimit {
             rlim_cur: 0,
@@ -98,26 +377,95 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    // 2. Set up a panic hook to clean up the terminal if the app crashes.
+    // 2. Set up a panic hook to wipe the buffer and clean up the terminal if the app crashes.
+    // With panic = "abort" no destructors run, so the wipe has to happen here.
     let default_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
+        amnesia::mem_buffer::wipe_all();
         let _ = disable_raw_mode();
         let mut stdout = io::stdout();
-        let _ = execute!(stdout, LeaveAlternateScreen);
+        let _ = execute!(stdout, DisableBracketedPaste, LeaveAlternateScreen);
         default_hook(panic_info);
     }));
 
-    // Initialize terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // 3. Catch termination signals so the main loop can exit through the normal
+    // cleanup path (terminal restore + buffer zeroization on drop).
+    let terminate = Arc::new(AtomicBool::new(false));
+    for signal in [
+        signal_hook::consts::SIGINT,
+        signal_hook::consts::SIGTERM,
+        #[cfg(unix)]
+        signal_hook::consts::SIGHUP,
+    ] {
+        if signal_hook::flag::register(signal, Arc::clone(&terminate)).is_err() {
+            eprintln!("Warning: Failed to register handler for signal {}.", signal);
+        }
+    }
 
     let mut editor = Editor::new(idle_secs, ttl, encryption_key, read_only);
+    editor.max_load_attempts = config.max_load_attempts.filter(|&n| n > 0);
+    editor.tab_width = config.tab_width.unwrap_or(4).max(1);
+    editor.max_buffer_bytes = config.max_buffer_bytes;
+    editor.wrap = config.wrap.unwrap_or(false);
+    editor.show_whitespace = config.show_whitespace.unwrap_or(false);
+    editor.line_numbers = config.line_numbers.unwrap_or_default();
+    editor.redacted = config.redact.unwrap_or(false);
+    #[cfg(feature = "spellcheck")]
+    {
+        editor.spell_check = config.spell_check.unwrap_or(false) && dictionary.is_some();
+        editor.dictionary = dictionary;
+    }
+    editor.highlight_current_line = config.highlight_current_line.unwrap_or(false);
+    if config.ruler.unwrap_or(false) {
+        editor.ruler = Some(config.ruler_column.unwrap_or(80));
+    }
+    editor.read_only_output = config.read_only_output.unwrap_or(true);
+    editor.key_bindings = config.key_bindings();
+    editor.scrub_on_overwrite = config.scrub_on_overwrite.unwrap_or(false);
+    editor.crlf_on_save = config.line_ending.unwrap_or_default() == LineEnding::Crlf;
+    editor.keyfile = args.keyfile.clone();
+    editor.recipients = args.recipients.clone();
+    editor.identity = args.identity.clone();
+    editor.min_password_score = config.min_password_score;
+    if let Some(mut password) = unlock_password.take() {
+        let result = editor.set_unlock_password(&password);
+        password.zeroize();
+        result?;
+    }
+    if let (Some(path), Some(mut password)) = (&config.autosave, autosave_password.take()) {
+        let interval = Duration::from_secs_f64(config.autosave_interval.unwrap_or(60.0));
+        // The length was checked against SECRET_CAPACITY when it was entered.
+        let _ = editor.set_autosave(PathBuf::from(path), interval, &password);
+        password.zeroize();
+    }
+    editor.swap_active =
+        config.warn_on_swap.unwrap_or(true) && amnesia::mem_buffer::swap_enabled().unwrap_or(false);
+    editor.key_rotation = config.key_rotation.map(Duration::from_secs_f64);
+    if let Some(minutes) = config.ttl_extension {
+        editor.ttl_extension = Some(Duration::from_secs_f64(minutes * 60.0));
+    }
+    editor.max_ttl_extensions = config.max_ttl_extensions;
+    editor.reading_wpm = config.reading_wpm.unwrap_or(200);
+    if let Some(secs) = config.status_timeout_secs {
+        editor.status_timeout = Duration::from_secs_f64(secs);
+    }
+    if let Some(format) = &config.status_format {
+        editor.status_format = format.clone();
+    }
+    if let Some(theme) = &config.theme {
+        editor.theme = theme.to_theme();
+    }
 
     if !initial_content.is_empty() {
-        editor.storage.update(&initial_content);
+        let mut normalized = amnesia::tui_app::normalize_newlines(&initial_content);
+        editor.storage.update(&normalized);
+        normalized.zeroize();
+        if args.file.is_some() {
+            editor.mark_saved();
+        }
+        if damaged_text {
+            editor.set_status(amnesia::tui_app::DAMAGED_TEXT_WARNING);
+        }
         // Initial content might be sensitive, so we should zeroize it?
         // String doesn't implement Zeroize automatically.
         // But `editor.storage.update` takes &str.
@@ -130,48 +478,191 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         key.zeroize();
     }
 
+    // Refuse to start before the terminal is taken over, so the message
+    // lands on the normal screen.
+    if strict_memory && !editor.is_memory_locked() {
+        drop(editor);
+        eprintln!("Error: Failed to lock memory in RAM (strict_memory is on). Memory wiped.");
+        std::process::exit(1);
+    }
+
+    // Initialize terminal
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    // Pastes arrive as one event instead of a keystroke per character.
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut panic_exit = false;
+    let mut unlocked_exit = false;
+
     loop {
-        // 1. Check for timeout BEFORE drawing or polling
-        if editor.is_timed_out() {
+        // 1. Check for timeout or termination signal BEFORE drawing or polling
+        if editor.is_expired() || terminate.load(Ordering::Relaxed) {
             break;
         }
+        if editor.is_idle() {
+            if !lock_on_idle {
+                break;
+            }
+            editor.lock();
+        }
+        // Growing a buffer can lose the lock, so this is checked every pass.
+        if strict_memory && !editor.is_memory_locked() {
+            editor.wipe();
+            unlocked_exit = true;
+            break;
+        }
+        editor.rotate_key_if_due();
+        editor.autosave_if_due();
 
         terminal.draw(|f| editor.draw(f))?;
 
         if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Esc => {
-                        if editor.input_mode != amnesia::tui_app::InputMode::Normal {
-                            editor.exit_popup();
-                        } else {
-                            break;
+            match event::read()? {
+                Event::Paste(mut text) => {
+                    // insert_str turns the \r line breaks terminals send into \n.
+                    editor.insert_str(&text);
+                    text.zeroize();
+                }
+                Event::Key(key) => {
+                    let action = editor.key_bindings.action_for(&key);
+                    if action == Some(Action::PanicWipe) {
+                        editor.wipe();
+                        panic_exit = true;
+                        break;
+                    }
+                    if editor.input_mode == InputMode::Locked {
+                        // Only the unlock prompt takes input.
+                        if action == Some(Action::TogglePasswordVisibility) {
+                            editor.toggle_password_visibility();
+                            continue;
+                        }
+                        match key.code {
+                            KeyCode::Enter => editor.handle_newline(),
+                            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                editor.handle_input(c)
+                            }
+                            KeyCode::Backspace => editor.delete_backspace(),
+                            KeyCode::Esc => editor.exit_popup(),
+                            _ => {}
                         }
+                        continue;
                     }
-                    KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        editor.toggle_markdown();
+                    if editor.input_mode == InputMode::Help {
+                        if key.code == KeyCode::Esc || action == Some(Action::Help) {
+                            editor.toggle_help();
+                        }
+                        continue;
+                    }
+                    // Esc closes popups whatever Quit is bound to.
+                    if key.code == KeyCode::Esc && editor.input_mode != InputMode::Normal {
+                        editor.exit_popup();
+                        continue;
                     }
-                    KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        editor.enter_save_mode();
+                    if let Some(action) = action {
+                        match action {
+                            Action::Help => editor.toggle_help(),
+                            Action::Quit => {
+                                if editor.input_mode != InputMode::Normal {
+                                    editor.exit_popup();
+                                } else {
+                                    break;
+                                }
+                            }
+                            Action::MarkdownToggle => editor.toggle_markdown(),
+                            Action::SplitPreview => editor.toggle_split_preview(),
+                            Action::Save => editor.enter_save_mode(),
+                            Action::Open => editor.enter_open_mode(),
+                            Action::InsertFile => editor.enter_insert_file_mode(),
+                            Action::NewTab => editor.new_tab(),
+                            Action::NextTab => editor.next_tab(),
+                            Action::GotoTab1
+                            | Action::GotoTab2
+                            | Action::GotoTab3
+                            | Action::GotoTab4
+                            | Action::GotoTab5
+                            | Action::GotoTab6
+                            | Action::GotoTab7
+                            | Action::GotoTab8
+                            | Action::GotoTab9 => {
+                                if let Some(index) = action.tab_index() {
+                                    editor.switch_tab(index)
+                                }
+                            }
+                            Action::CloseTab => editor.close_tab(),
+                            Action::ClearNote => editor.request_clear(),
+                            Action::GotoLine => editor.enter_goto_mode(),
+                            Action::DeleteWordBackward => editor.delete_word_backward(),
+                            Action::SelectAll => editor.select_all(),
+                            Action::ExtendTtl => editor.extend_ttl(),
+                            Action::TogglePasswordVisibility => editor.toggle_password_visibility(),
+                            Action::RandomFilename => editor.generate_filename(),
+                            Action::CopyClipboard => {
+                                if let Some(mut sequence) = editor.copy_to_clipboard() {
+                                    let backend = terminal.backend_mut();
+                                    backend.write_all(sequence.as_bytes())?;
+                                    backend.flush()?;
+                                    sequence.zeroize();
+                                }
+                            }
+                            Action::WrapToggle => editor.toggle_wrap(),
+                            Action::WhitespaceToggle => editor.toggle_whitespace(),
+                            Action::LineNumbers => editor.cycle_line_numbers(),
+                            Action::Redact => editor.toggle_redaction(),
+                            Action::SpellCheckToggle => editor.toggle_spell_check(),
+                            Action::Top => editor.move_to_start(),
+                            Action::Bottom => editor.move_to_end(),
+                            Action::PanicWipe => {} // Handled above
+                        }
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Enter => editor.handle_newline(),
+                        KeyCode::Char(c) => editor.handle_input(c),
+                        KeyCode::Backspace => editor.delete_backspace(),
+                        KeyCode::Tab => editor.complete_path(),
+                        KeyCode::Left => editor.move_cursor(-1),
+                        KeyCode::Right => editor.move_cursor(1),
+                        KeyCode::Up => editor.move_cursor_lineal(-1),
+                        KeyCode::Down => editor.move_cursor_lineal(1),
+                        _ => {}
                     }
-                    KeyCode::Enter => editor.handle_newline(),
-                    KeyCode::Char(c) => editor.handle_input(c),
-                    KeyCode::Backspace => editor.delete_backspace(),
-                    KeyCode::Left => editor.move_cursor(-1),
-                    KeyCode::Right => editor.move_cursor(1),
-                    KeyCode::Up => editor.move_cursor_lineal(-1),
-                    KeyCode::Down => editor.move_cursor_lineal(1),
-                    _ => {}
                 }
+                _ => {}
             }
         }
     }
 
+    // Wipe the buffer before anything else can fail
+    drop(editor);
+
     // Cleanup terminal
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )?;
     terminal.show_cursor()?;
 
+    if panic_exit {
+        // Leave a blank terminal with no trace of the session in scrollback.
+        execute!(
+            io::stdout(),
+            Clear(ClearType::All),
+            Clear(ClearType::Purge),
+            MoveTo(0, 0)
+        )?;
+        return Ok(());
+    }
+
+    if unlocked_exit {
+        eprintln!("Error: Failed to lock memory in RAM (strict_memory is on). Memory wiped.");
+        std::process::exit(1);
+    }
+
     println!("\r\nAmnesia: Memory wiped. Goodbye.");
     Ok(())
 }
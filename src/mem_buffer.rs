@@ -1,38 +1,290 @@
-use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
 use chacha20::ChaCha20;
+#[cfg(unix)]
 use libc::{c_void, mlock, munlock};
+use std::io;
+use std::ops::Range;
+use std::sync::Mutex;
 use zeroize::Zeroize;
 
+/// Heap regions (address, length) owned by live buffers, so that panic and
+/// abort paths can wipe them without access to the owning `MemoryBuffer`.
+static LIVE_REGIONS: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+fn register_region(ptr: *const u8, len: usize) {
+    if let Ok(mut regions) = LIVE_REGIONS.lock() {
+        regions.push((ptr as usize, len));
+    }
+}
+
+fn unregister_region(ptr: *const u8) {
+    if let Ok(mut regions) = LIVE_REGIONS.lock() {
+        regions.retain(|(addr, _)| *addr != ptr as usize);
+    }
+}
+
+/// Zeroes every live buffer and key in place.
+/// Intended for the panic hook: the process is going down and the owning
+/// buffers will not get a chance to run their `Drop`.
+pub fn wipe_all() {
+    // try_lock: the panic may have happened while the registry was held.
+    if let Ok(regions) = LIVE_REGIONS.try_lock() {
+        for &(addr, len) in regions.iter() {
+            unsafe {
+                std::slice::from_raw_parts_mut(addr as *mut u8, len).zeroize();
+            }
+        }
+    }
+}
+
+/// Pins a region so the OS will not swap it out.
+/// Returns 0 on success, otherwise the platform's error value.
+#[cfg(unix)]
+fn lock_region(ptr: *const u8, len: usize) -> i32 {
+    if unsafe { mlock(ptr as *const c_void, len) } == 0 {
+        return 0;
+    }
+    io::Error::last_os_error().raw_os_error().unwrap_or(-1)
+}
+
+#[cfg(unix)]
+fn unlock_region(ptr: *const u8, len: usize) {
+    unsafe {
+        let _ = munlock(ptr as *const c_void, len);
+    }
+}
+
+#[cfg(windows)]
+fn lock_region(ptr: *const u8, len: usize) -> i32 {
+    use windows_sys::Win32::System::Memory::VirtualLock;
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, GetProcessWorkingSetSize, SetProcessWorkingSetSize,
+    };
+
+    if len == 0 {
+        return 0;
+    }
+    unsafe {
+        if VirtualLock(ptr.cast(), len) != 0 {
+            return 0;
+        }
+        // The default working set only allows a few locked pages; grow it and retry.
+        let process = GetCurrentProcess();
+        let (mut min, mut max) = (0usize, 0usize);
+        if GetProcessWorkingSetSize(process, &mut min, &mut max) != 0
+            && SetProcessWorkingSetSize(process, min + len, max + len) != 0
+            && VirtualLock(ptr.cast(), len) != 0
+        {
+            return 0;
+        }
+    }
+    std::io::Error::last_os_error().raw_os_error().unwrap_or(-1)
+}
+
+#[cfg(windows)]
+fn unlock_region(ptr: *const u8, len: usize) {
+    use windows_sys::Win32::System::Memory::VirtualUnlock;
+
+    if len == 0 {
+        return;
+    }
+    unsafe {
+        let _ = VirtualUnlock(ptr.cast(), len);
+    }
+}
+
+/// The whole pages inside a region, as (start, length). madvise works on
+/// whole pages, and the partial pages at either end may hold other
+/// allocations, which must keep their default advice.
+#[cfg(target_os = "linux")]
+fn owned_pages(ptr: *const u8, len: usize) -> Option<(usize, usize)> {
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let start = (ptr as usize).div_ceil(page) * page;
+    let end = (ptr as usize + len) / page * page;
+    (start < end).then(|| (start, end - start))
+}
+
+/// Excludes the region's whole pages from core dumps and forked children.
+/// Lift it with `include_region` before the memory is freed: the allocator
+/// hands those pages to other allocations, and a forked child would crash
+/// on touching them.
+#[cfg(target_os = "linux")]
+fn exclude_region(ptr: *const u8, len: usize) {
+    if let Some((start, len)) = owned_pages(ptr, len) {
+        unsafe {
+            let _ = libc::madvise(start as *mut c_void, len, libc::MADV_DONTDUMP);
+            let _ = libc::madvise(start as *mut c_void, len, libc::MADV_DONTFORK);
+        }
+    }
+}
+
+/// Undoes `exclude_region` for the same region.
+#[cfg(target_os = "linux")]
+fn include_region(ptr: *const u8, len: usize) {
+    if let Some((start, len)) = owned_pages(ptr, len) {
+        unsafe {
+            let _ = libc::madvise(start as *mut c_void, len, libc::MADV_DODUMP);
+            let _ = libc::madvise(start as *mut c_void, len, libc::MADV_DOFORK);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exclude_region(_ptr: *const u8, _len: usize) {}
+
+#[cfg(not(target_os = "linux"))]
+fn include_region(_ptr: *const u8, _len: usize) {}
+
+/// Whether the system has swap space active, if that can be determined.
+/// Unpinned memory may be written there.
+pub fn swap_enabled() -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        // One header line, then one line per active swap area.
+        let swaps = std::fs::read_to_string("/proc/swaps").ok()?;
+        Some(swaps.lines().skip(1).any(|l| !l.trim().is_empty()))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let mut usage: libc::xsw_usage = unsafe { std::mem::zeroed() };
+        let mut size = std::mem::size_of::<libc::xsw_usage>();
+        let rc = unsafe {
+            libc::sysctlbyname(
+                c"vm.swapusage".as_ptr(),
+                &mut usage as *mut libc::xsw_usage as *mut c_void,
+                &mut size,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        (rc == 0).then_some(usage.xsu_total > 0)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// XORs the ChaCha20 keystream over `buf` when a key is present.
+/// `offset` is the position of `buf` within the buffer, so any sub-range
+/// can be decrypted or re-encrypted on its own.
+fn apply_keystream(key: Option<&[u8; 32]>, offset: usize, buf: &mut [u8]) {
+    if let Some(key) = key {
+        let mut k = *key;
+        let mut cipher = ChaCha20::new(&k.into(), &[0u8; 12].into());
+        cipher.seek(offset as u64);
+        cipher.apply_keystream(buf);
+        k.as_mut_slice().zeroize();
+    }
+}
+
+/// Length of a UTF-8 sequence from its lead byte.
+fn utf8_width(lead: u8) -> usize {
+    match lead {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    }
+}
+
 /// A buffer that is pinned in RAM and zeroed on drop.
 /// Optionally encrypted with an ephemeral key.
 pub struct MemoryBuffer {
     data: Vec<u8>,
-    key: Option<[u8; 32]>,
+    len: usize,      // Plaintext length in bytes; everything past it is encrypted zeros
+    chars: usize,    // Plaintext length in chars
+    generation: u64, // Bumped on every change so readers can cache the plaintext
+    // Boxed so its address stays stable for `wipe_all`.
+    key: Option<Box<[u8; 32]>>,
+    locked: bool, // Whether `data` is pinned; growing can lose this
 }
 
 impl MemoryBuffer {
     /// Creates a new pinned memory buffer of the given size.
-    pub fn new(size: usize, key: Option<[u8; 32]>) -> Self {
+    /// Fails if the memory can't be locked in RAM, e.g. because
+    /// `RLIMIT_MEMLOCK` is too low.
+    pub fn new(size: usize, key: Option<[u8; 32]>) -> io::Result<Self> {
+        let (buffer, res) = Self::allocate(size, key);
+        if res != 0 {
+            // Dropping the buffer zeroes it and the key.
+            return Err(io::Error::from_raw_os_error(res));
+        }
+        Ok(buffer)
+    }
+
+    /// Like `new`, but keeps going with a warning if the memory can't be
+    /// locked. Check `is_locked` to find out.
+    pub fn new_lenient(size: usize, key: Option<[u8; 32]>) -> Self {
+        let (buffer, res) = Self::allocate(size, key);
+        if res != 0 {
+            eprintln!(
+                "Warning: Failed to lock memory in RAM. mlock returned {}",
+                res
+            );
+        }
+        buffer
+    }
+
+    /// Allocates and pins the buffer, returning the pinning result as well.
+    fn allocate(size: usize, key: Option<[u8; 32]>) -> (Self, i32) {
         let mut data = vec![0u8; size];
 
         // Pin the memory to prevent swapping.
-        unsafe {
-            let res = mlock(data.as_ptr() as *const c_void, size);
-            if res != 0 {
-                eprintln!(
-                    "Warning: Failed to lock memory in RAM. mlock returned {}",
-                    res
-                );
-            }
-        }
+        let res = lock_region(data.as_ptr(), size);
+        exclude_region(data.as_ptr(), size);
 
-        if let Some(mut k) = key {
-            let mut cipher = ChaCha20::new(&k.into(), &[0u8; 12].into());
-            cipher.apply_keystream(&mut data);
-            k.as_mut_slice().zeroize();
+        let key = key.map(Box::new);
+        if let Some(k) = &key {
+            register_region(k.as_ptr(), k.len());
         }
+        register_region(data.as_ptr(), data.len());
+
+        apply_keystream(key.as_deref(), 0, &mut data);
+
+        let buffer = MemoryBuffer {
+            data,
+            len: 0,
+            chars: 0,
+            generation: 0,
+            key,
+            locked: res == 0,
+        };
+        (buffer, res)
+    }
+
+    /// Returns true if the whole buffer is currently pinned in RAM.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Content length in bytes.
+    pub fn byte_len(&self) -> usize {
+        self.len
+    }
+
+    /// Bytes allocated (and pinned) for content. Typing past this
+    /// reallocates, which zeroes and re-pins the whole buffer.
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Bytes of the capacity in use; the same as `byte_len`.
+    pub fn used_bytes(&self) -> usize {
+        self.len
+    }
 
-        MemoryBuffer { data, key }
+    /// Content length in chars.
+    pub fn char_len(&self) -> usize {
+        self.chars
+    }
+
+    /// A counter that changes whenever the content does.
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
     /// Returns true if the buffer is currently encrypted.
@@ -41,19 +293,13 @@ impl MemoryBuffer {
     }
 
     /// Access the underlying data as a string (assuming UTF-8).
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
-        let mut buffer = self.data.clone();
-
-        if let Some(mut key) = self.key {
-            let mut cipher = ChaCha20::new(&key.into(), &[0u8; 12].into());
-            cipher.apply_keystream(&mut buffer);
-            key.as_mut_slice().zeroize();
-        }
+        let mut buffer = self.data[..self.len].to_vec();
+        apply_keystream(self.key.as_deref(), 0, &mut buffer);
 
-        // Find the first null byte or end of string
-        let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
-
-        let result = String::from_utf8_lossy(&buffer[..len]).to_string();
+        // `len` marks the end, so embedded NUL bytes are kept.
+        let result = String::from_utf8_lossy(&buffer).to_string();
         buffer.as_mut_slice().zeroize();
         result
     }
@@ -63,46 +309,216 @@ impl MemoryBuffer {
         let bytes = text.as_bytes();
         let new_len = bytes.len();
 
-        // 1. Ensure capacity (scalable!)
-        self.ensure_capacity(new_len);
+        // 1. Ensure capacity (scalable!). Growing zeroes the whole buffer,
+        // so all of it has to be re-encrypted afterwards.
+        let dirty = if self.ensure_capacity(new_len) {
+            self.data.len()
+        } else {
+            self.len.max(new_len)
+        };
 
-        // 2. Clear old content (preserving the rest of the buffer)
-        self.data.as_mut_slice().zeroize();
+        // 2. Clear old content; the tail past `dirty` is still encrypted zeros
+        self.data[..dirty].zeroize();
 
         // 3. Copy new content
         self.data[..new_len].copy_from_slice(bytes);
 
-        // 4. Always encrypt the entire buffer to maintain consistency
-        if let Some(mut key) = self.key {
-            let mut cipher = ChaCha20::new(&key.into(), &[0u8; 12].into());
-            cipher.apply_keystream(&mut self.data);
-            key.as_mut_slice().zeroize();
+        // 4. Re-encrypt only the region that was touched
+        apply_keystream(self.key.as_deref(), 0, &mut self.data[..dirty]);
+        self.len = new_len;
+        self.chars = text.chars().count();
+        self.generation += 1;
+    }
+
+    /// Byte offset of the `char_idx`-th character, clamped to the content length.
+    /// Decrypts in small chunks so no full plaintext copy is made.
+    pub fn byte_offset(&self, char_idx: usize) -> usize {
+        let mut chunk = [0u8; 256];
+        let mut seen = 0;
+        let mut pos = 0;
+        while pos < self.len {
+            let n = (self.len - pos).min(chunk.len());
+            chunk[..n].copy_from_slice(&self.data[pos..pos + n]);
+            apply_keystream(self.key.as_deref(), pos, &mut chunk[..n]);
+            for (i, &b) in chunk[..n].iter().enumerate() {
+                // Count UTF-8 lead bytes, skipping continuation bytes.
+                if b & 0xC0 != 0x80 {
+                    if seen == char_idx {
+                        chunk.zeroize();
+                        return pos + i;
+                    }
+                    seen += 1;
+                }
+            }
+            pos += n;
         }
+        chunk.zeroize();
+        self.len
     }
 
-    fn ensure_capacity(&mut self, required_size: usize) {
-        if required_size <= self.data.len() {
+    /// Inserts a character before the `char_idx`-th character.
+    pub fn insert_char(&mut self, char_idx: usize, ch: char) {
+        let mut encoded = [0u8; 4];
+        let at = self.byte_offset(char_idx);
+        self.insert_bytes(at, ch.encode_utf8(&mut encoded).as_bytes());
+        encoded.zeroize();
+    }
+
+    /// Inserts a string before the `char_idx`-th character.
+    pub fn insert_str(&mut self, char_idx: usize, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        let at = self.byte_offset(char_idx);
+        self.insert_bytes(at, s.as_bytes());
+    }
+
+    /// Inserts a string at byte offset `at`, moved back to a char boundary
+    /// if it falls inside a character.
+    pub fn insert_str_at(&mut self, at: usize, s: &str) {
+        if s.is_empty() {
             return;
         }
+        let at = self.floor_char_boundary(at);
+        self.insert_bytes(at, s.as_bytes());
+    }
+
+    /// Removes and returns the `char_idx`-th character, if there is one.
+    pub fn remove_char(&mut self, char_idx: usize) -> Option<char> {
+        let at = self.byte_offset(char_idx);
+        if at >= self.len {
+            return None;
+        }
+
+        let mut head = [0u8; 4];
+        let n = (self.len - at).min(head.len());
+        head[..n].copy_from_slice(&self.data[at..at + n]);
+        apply_keystream(self.key.as_deref(), at, &mut head[..n]);
+        let width = utf8_width(head[0]).min(n);
+        let removed = std::str::from_utf8(&head[..width])
+            .ok()
+            .and_then(|s| s.chars().next());
+        head.zeroize();
+
+        self.remove_range(at..at + width);
+        removed
+    }
+
+    /// Removes the bytes in `range`, with both ends moved back to char
+    /// boundaries.
+    pub fn remove_range(&mut self, range: Range<usize>) {
+        let start = self.floor_char_boundary(range.start);
+        let end = self.floor_char_boundary(range.end);
+        if start >= end {
+            return;
+        }
+
+        // Decrypt the tail in place, shift it left over the removed bytes,
+        // then re-encrypt the tail (including the now-zeroed end).
+        let len = self.len;
+        apply_keystream(self.key.as_deref(), start, &mut self.data[start..len]);
+        let removed_chars = self.data[start..end]
+            .iter()
+            .filter(|&&b| b & 0xC0 != 0x80)
+            .count();
+        self.data.copy_within(end..len, start);
+        self.data[len - (end - start)..len].zeroize();
+        apply_keystream(self.key.as_deref(), start, &mut self.data[start..len]);
+
+        self.len -= end - start;
+        self.chars -= removed_chars;
+        self.generation += 1;
+    }
+
+    /// The char boundary at or before byte offset `at`, clamped to the
+    /// content length. Decrypts at most the few bytes it looks at.
+    fn floor_char_boundary(&self, at: usize) -> usize {
+        let mut at = at.min(self.len);
+        while at > 0 && at < self.len {
+            let mut byte = [self.data[at]];
+            apply_keystream(self.key.as_deref(), at, &mut byte);
+            let continuation = byte[0] & 0xC0 == 0x80;
+            byte.zeroize();
+            if !continuation {
+                break;
+            }
+            at -= 1;
+        }
+        at
+    }
+
+    fn insert_bytes(&mut self, at: usize, bytes: &[u8]) {
+        let new_len = self.len + bytes.len();
+        if new_len > self.data.len() {
+            // Growing wipes the buffer, so go through a full rewrite.
+            let mut content = self.to_string();
+            content.insert_str(at.min(content.len()), &String::from_utf8_lossy(bytes));
+            self.update(&content);
+            content.zeroize();
+            return;
+        }
+
+        // Decrypt from the insertion point, shift right, write, re-encrypt.
+        apply_keystream(self.key.as_deref(), at, &mut self.data[at..new_len]);
+        self.data.copy_within(at..self.len, at + bytes.len());
+        self.data[at..at + bytes.len()].copy_from_slice(bytes);
+        apply_keystream(self.key.as_deref(), at, &mut self.data[at..new_len]);
+
+        self.len = new_len;
+        self.chars += bytes.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+        self.generation += 1;
+    }
+
+    /// Re-encrypts the buffer under `new_key` and overwrites the old key.
+    /// Works chunk by chunk in place, so at most one small chunk is ever
+    /// plaintext. Does nothing for unencrypted buffers.
+    pub fn rotate_key(&mut self, new_key: &[u8; 32]) {
+        let Some(key) = self.key.as_deref_mut() else {
+            return;
+        };
+        for (i, chunk) in self.data.chunks_mut(256).enumerate() {
+            apply_keystream(Some(key), i * 256, chunk);
+            apply_keystream(Some(new_key), i * 256, chunk);
+        }
+        // Overwrite in place so the registered address stays valid.
+        key.copy_from_slice(new_key);
+    }
+
+    /// Zeroes the whole buffer immediately, leaving it empty.
+    pub fn wipe(&mut self) {
+        self.data.as_mut_slice().zeroize();
+        apply_keystream(self.key.as_deref(), 0, &mut self.data);
+        self.len = 0;
+        self.chars = 0;
+        self.generation += 1;
+    }
+
+    /// Grows the buffer if needed. Returns true if it was reallocated.
+    fn ensure_capacity(&mut self, required_size: usize) -> bool {
+        if required_size <= self.data.len() {
+            return false;
+        }
 
         // We need to grow. To be safe with mlock, we'll:
         // 1. Unlock and zero current memory
-        unsafe {
-            let _ = munlock(self.data.as_ptr() as *const c_void, self.data.len());
-        }
+        unlock_region(self.data.as_ptr(), self.data.len());
+        include_region(self.data.as_ptr(), self.data.len());
         self.data.as_mut_slice().zeroize();
+        unregister_region(self.data.as_ptr());
 
         // 2. Resize (we'll grow to required_size or double the current size, whichever is larger)
         let grow_to = required_size.max(self.data.len() * 2);
         self.data.resize(grow_to, 0u8);
 
         // 3. Pin the new memory
-        unsafe {
-            let res = mlock(self.data.as_ptr() as *const c_void, self.data.len());
-            if res != 0 {
-                eprintln!("Warning: Failed to lock NEW memory in RAM ({}).", res);
-            }
+        let res = lock_region(self.data.as_ptr(), self.data.len());
+        if res != 0 {
+            eprintln!("Warning: Failed to lock NEW memory in RAM ({}).", res);
         }
+        self.locked = res == 0;
+        exclude_region(self.data.as_ptr(), self.data.len());
+        register_region(self.data.as_ptr(), self.data.len());
+        true
     }
 }
 
@@ -111,12 +527,77 @@ impl Drop for MemoryBuffer {
         // Explicitly overwrite with zeros before unlocking.
         self.data.as_mut_slice().zeroize();
 
-        if let Some(mut key) = self.key {
+        if let Some(key) = &mut self.key {
             key.as_mut_slice().zeroize();
+            unregister_region(key.as_ptr());
         }
 
-        unsafe {
-            let _ = munlock(self.data.as_ptr() as *const c_void, self.data.len());
+        unlock_region(self.data.as_ptr(), self.data.len());
+        include_region(self.data.as_ptr(), self.data.len());
+        unregister_region(self.data.as_ptr());
+    }
+}
+
+/// A plaintext string whose allocation is pinned in RAM and zeroed whenever
+/// it is replaced or dropped. Used to cache decrypted content between frames.
+pub struct LockedString {
+    text: String,
+    locked: bool, // Whether the current allocation is pinned
+}
+
+impl Default for LockedString {
+    fn default() -> Self {
+        // Nothing is allocated yet, so there is nothing to pin.
+        Self {
+            text: String::new(),
+            locked: true,
+        }
+    }
+}
+
+impl LockedString {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the content, reallocating (and re-pinning) only when it must grow.
+    pub fn set(&mut self, s: &str) {
+        if s.len() > self.text.capacity() {
+            self.release();
+            self.text = String::with_capacity(s.len().max(64));
+            // Set from draw, with the terminal in raw mode, so a failure is
+            // only recorded; the status bar reports it via `is_locked`.
+            self.locked = lock_region(self.text.as_ptr(), self.text.capacity()) == 0;
+            exclude_region(self.text.as_ptr(), self.text.capacity());
+            register_region(self.text.as_ptr(), self.text.capacity());
+        } else {
+            // Zeroes the full capacity, not just the old length.
+            self.text.zeroize();
         }
+        self.text.push_str(s);
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns true if the allocation is pinned in RAM.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    fn release(&mut self) {
+        self.text.zeroize();
+        if self.text.capacity() > 0 {
+            unlock_region(self.text.as_ptr(), self.text.capacity());
+            include_region(self.text.as_ptr(), self.text.capacity());
+            unregister_region(self.text.as_ptr());
+        }
+    }
+}
+
+impl Drop for LockedString {
+    fn drop(&mut self) {
+        self.release();
     }
 }
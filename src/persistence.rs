@@ -1,25 +1,105 @@
 use argon2::{password_hash::SaltString, Argon2};
-use chacha20poly1305::aead::{Aead, KeyInit};
-use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chacha20poly1305::aead::{Aead, AeadInPlace, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
 use rand::{rngs::OsRng, RngCore};
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use zeroize::Zeroize;
 
-const MAGIC_BYTES: &[u8; 8] = b"AMNESIO2"; // Version 2 uses Argon2id
+const MAGIC_CHUNKED: &[u8; 8] = b"AMNESIO7"; // Version 7 adds the key source to version 5
+const MAGIC_BYTES: &[u8; 8] = b"AMNESIO6"; // Version 6 records the key source after the magic
+const MAGIC_V5: &[u8; 8] = b"AMNESIO5"; // Version 5 streams large notes in AEAD frames
+const MAGIC_V4: &[u8; 8] = b"AMNESIO4"; // Version 4 authenticates the header as AAD
+const MAGIC_V3: &[u8; 8] = b"AMNESIO3"; // Version 3 adds an encrypted metadata block
+const MAGIC_V2: &[u8; 8] = b"AMNESIO2"; // Version 2 uses Argon2id
+const MAGIC_PREFIX: &[u8; 7] = b"AMNESIO"; // Followed by the version digit
+
+/// The file format version written by `save_encrypted` for regular notes.
+/// Files at or above this version need no migration.
+pub const CURRENT_VERSION: u8 = 6;
+/// The chunked format used for notes larger than `CHUNK_THRESHOLD`.
+pub const CHUNKED_VERSION: u8 = 7;
+/// Payloads larger than this are saved in 64KB frames, so saving and loading
+/// never hold the whole plaintext and ciphertext at once.
+pub const CHUNK_THRESHOLD: usize = 1024 * 1024;
 const SALT_LEN: usize = 16;
 const NONCE_LEN: usize = 12;
+const NONCE_PREFIX_LEN: usize = 7; // Chunked nonces: prefix, u32 counter, last-frame flag
+const CHUNK_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
 const KEY_LEN: usize = 32;
+const KEY_SOURCE_VERSION: u8 = 6; // First version with a key source byte
+
+/// Minimum size of a keyfile; it should hold at least a key's worth of entropy.
+pub const MIN_KEYFILE_LEN: u64 = 32;
+/// Keyfiles larger than this are rejected rather than read into memory.
+pub const MAX_KEYFILE_LEN: u64 = 1024 * 1024;
+
+/// Minimum password length accepted when saving.
+pub const MIN_PASSWORD_LEN: usize = 8;
 
 #[derive(Debug)]
 pub enum PersistenceError {
     Io(std::io::Error),
     Encryption(String),
+    /// The file is not an .amnesio file, or is too short to be a whole one.
+    InvalidFileFormat,
+    /// The file looks intact but failed authentication: usually a wrong
+    /// password or keyfile, though tampering looks the same.
+    DecryptionFailed,
+    UnsupportedVersion(u8),
+    /// The file was locked with the other kind of key.
+    WrongKeySource(KeySource),
+    InvalidKeyfile(String),
+}
+
+/// Which `PersistenceError` variant an error is, without its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Io,
+    Encryption,
     InvalidFileFormat,
     DecryptionFailed,
+    UnsupportedVersion,
+    WrongKeySource,
+    InvalidKeyfile,
+}
+
+impl PersistenceError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PersistenceError::Io(_) => ErrorKind::Io,
+            PersistenceError::Encryption(_) => ErrorKind::Encryption,
+            PersistenceError::InvalidFileFormat => ErrorKind::InvalidFileFormat,
+            PersistenceError::DecryptionFailed => ErrorKind::DecryptionFailed,
+            PersistenceError::UnsupportedVersion(_) => ErrorKind::UnsupportedVersion,
+            PersistenceError::WrongKeySource(_) => ErrorKind::WrongKeySource,
+            PersistenceError::InvalidKeyfile(_) => ErrorKind::InvalidKeyfile,
+        }
+    }
 }
 
+/// I/O errors compare by `io::ErrorKind`, as `io::Error` itself can't be
+/// compared; everything else compares by value.
+impl PartialEq for PersistenceError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PersistenceError::Io(a), PersistenceError::Io(b)) => a.kind() == b.kind(),
+            (PersistenceError::Encryption(a), PersistenceError::Encryption(b)) => a == b,
+            (PersistenceError::UnsupportedVersion(a), PersistenceError::UnsupportedVersion(b)) => {
+                a == b
+            }
+            (PersistenceError::WrongKeySource(a), PersistenceError::WrongKeySource(b)) => a == b,
+            (PersistenceError::InvalidKeyfile(a), PersistenceError::InvalidKeyfile(b)) => a == b,
+            _ => self.kind() == other.kind() && !matches!(self, PersistenceError::Io(_)),
+        }
+    }
+}
+
+impl Eq for PersistenceError {}
+
 impl From<std::io::Error> for PersistenceError {
     fn from(e: std::io::Error) -> Self {
         PersistenceError::Io(e)
@@ -32,83 +112,823 @@ impl std::fmt::Display for PersistenceError {
             PersistenceError::Io(e) => write!(f, "IO Error: {}", e),
             PersistenceError::Encryption(e) => write!(f, "Encryption Error: {}", e),
             PersistenceError::InvalidFileFormat => {
-                write!(f, "Invalid file format (not a v2 .amnesio file)")
+                write!(
+                    f,
+                    "Invalid file format (not an .amnesio file, or truncated)"
+                )
             }
             PersistenceError::DecryptionFailed => write!(f, "Decryption failed (wrong password?)"),
+            PersistenceError::UnsupportedVersion(v) if *v > CURRENT_VERSION => write!(
+                f,
+                "File format v{} was written by a newer amnesia; please upgrade",
+                v
+            ),
+            PersistenceError::UnsupportedVersion(v) => {
+                write!(f, "File format v{} is no longer supported", v)
+            }
+            PersistenceError::WrongKeySource(KeySource::Keyfile) => {
+                write!(f, "File is locked with a keyfile (use --keyfile)")
+            }
+            PersistenceError::WrongKeySource(KeySource::Password) => {
+                write!(f, "File is locked with a password")
+            }
+            PersistenceError::WrongKeySource(KeySource::Recipients) => {
+                write!(f, "File is encrypted to age recipients (use --identity)")
+            }
+            PersistenceError::InvalidKeyfile(e) => write!(f, "Invalid keyfile: {}", e),
         }
     }
 }
 
 impl std::error::Error for PersistenceError {}
 
+/// How a file's key is derived, or whether it is encrypted to age
+/// recipients. Stored in the header from v6 on; older files always use a
+/// password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    Password,
+    Keyfile,
+    /// Encrypted to age public keys (see `save_to_recipients`).
+    Recipients,
+}
+
+impl KeySource {
+    fn to_byte(self) -> u8 {
+        match self {
+            KeySource::Password => 0,
+            KeySource::Keyfile => 1,
+            KeySource::Recipients => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, PersistenceError> {
+        match byte {
+            0 => Ok(KeySource::Password),
+            1 => Ok(KeySource::Keyfile),
+            2 => Ok(KeySource::Recipients),
+            _ => Err(PersistenceError::InvalidFileFormat),
+        }
+    }
+}
+
+/// The secret a file key is derived from.
+#[derive(Clone, Copy)]
+pub enum Credential<'a> {
+    Password(&'a str),
+    /// The raw contents of a keyfile (see `read_keyfile`).
+    Keyfile(&'a [u8]),
+}
+
+impl Credential<'_> {
+    pub fn source(&self) -> KeySource {
+        match self {
+            Credential::Password(_) => KeySource::Password,
+            Credential::Keyfile(_) => KeySource::Keyfile,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Credential::Password(p) => p.as_bytes(),
+            Credential::Keyfile(k) => k,
+        }
+    }
+}
+
+/// Reads a keyfile's bytes for use as a `Credential::Keyfile`.
+/// The caller must zeroize the returned bytes once the key is derived.
+pub fn read_keyfile<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, PersistenceError> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len < MIN_KEYFILE_LEN {
+        return Err(PersistenceError::InvalidKeyfile(format!(
+            "too short (min {} bytes)",
+            MIN_KEYFILE_LEN
+        )));
+    }
+    if len > MAX_KEYFILE_LEN {
+        return Err(PersistenceError::InvalidKeyfile(format!(
+            "too large (max {} bytes)",
+            MAX_KEYFILE_LEN
+        )));
+    }
+    Ok(crate::secret::read_all_zeroizing(file)?)
+}
+
+/// Context saved with a note. It lives inside the encrypted payload, so it is
+/// authenticated and never visible without the password.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub title: Option<String>,
+    pub created: u64, // Unix seconds
+}
+
+impl FileMetadata {
+    /// Metadata for `content` as of now, titled after its first non-empty line.
+    pub fn for_content(content: &str) -> Self {
+        let title = content
+            .lines()
+            .map(|l| l.trim_start_matches('#').trim())
+            .find(|l| !l.is_empty())
+            .map(|l| l.chars().take(64).collect());
+        let created = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self { title, created }
+    }
+}
+
+/// Argon2id cost parameters used to derive a file key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// The parameters `save_encrypted` uses.
+    pub fn current() -> Self {
+        let params = argon2::Params::default();
+        Self {
+            memory_kib: params.m_cost(),
+            iterations: params.t_cost(),
+            parallelism: params.p_cost(),
+        }
+    }
+}
+
+/// What a successful save wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveInfo {
+    pub path: PathBuf,
+    pub bytes_written: u64,
+    pub version: u8,
+    pub kdf: Option<KdfParams>, // None for files saved to age recipients
+}
+
+/// Encrypts `content` with a key derived from `password` and writes it to `path`.
+/// With `read_only_output`, the saved file is marked read-only.
 pub fn save_encrypted<P: AsRef<Path>>(
     path: P,
     content: &str,
     password: &str,
-) -> Result<(), PersistenceError> {
-    // 1. Generate Salt and Nonce
-    let mut salt_bytes = [0u8; SALT_LEN];
-    let mut nonce_bytes = [0u8; NONCE_LEN];
-    OsRng.fill_bytes(&mut salt_bytes);
-    OsRng.fill_bytes(&mut nonce_bytes);
+    read_only_output: bool,
+) -> Result<SaveInfo, PersistenceError> {
+    let metadata = FileMetadata::for_content(content);
+    save_encrypted_with_metadata(path, content, password, read_only_output, false, &metadata)
+}
 
-    // 2. Derive Key using Argon2id
+/// Like `save_encrypted`, with explicit metadata. With `scrub_on_overwrite`,
+/// a file being replaced is overwritten with random bytes once the new one
+/// is in place (see `write_atomic_scrub`).
+pub fn save_encrypted_with_metadata<P: AsRef<Path>>(
+    path: P,
+    content: &str,
+    password: &str,
+    read_only_output: bool,
+    scrub_on_overwrite: bool,
+    metadata: &FileMetadata,
+) -> Result<SaveInfo, PersistenceError> {
+    save_with_credential(
+        path,
+        content,
+        Credential::Password(password),
+        read_only_output,
+        scrub_on_overwrite,
+        metadata,
+    )
+}
+
+/// Like `save_encrypted_with_metadata`, deriving the key from `credential`.
+/// The key source is recorded in the header so loading knows what to ask for.
+pub fn save_with_credential<P: AsRef<Path>>(
+    path: P,
+    content: &str,
+    credential: Credential,
+    read_only_output: bool,
+    scrub_on_overwrite: bool,
+    metadata: &FileMetadata,
+) -> Result<SaveInfo, PersistenceError> {
+    let meta =
+        toml::to_string(metadata).map_err(|e| PersistenceError::Encryption(e.to_string()))?;
+    let meta_len = (meta.len() as u32).to_le_bytes();
+    // Plaintext: [META_LEN u32 LE] [META (TOML)] [CONTENT]
+    let parts = [&meta_len[..], meta.as_bytes(), content.as_bytes()];
+
+    let version = if parts.iter().map(|p| p.len()).sum::<usize>() > CHUNK_THRESHOLD {
+        save_chunked(&path, &parts, credential, scrub_on_overwrite)?;
+        CHUNKED_VERSION
+    } else {
+        save_single(&path, &parts, credential, scrub_on_overwrite)?;
+        CURRENT_VERSION
+    };
+    finish_save(path, version, read_only_output, Some(KdfParams::current()))
+}
+
+/// Encrypts `content` to one or more age recipients (X25519 public keys)
+/// instead of a password, so only a matching identity can open it later
+/// (see `load_with_identity`). The saved file is marked read-only.
+pub fn save_to_recipients<P: AsRef<Path>>(
+    path: P,
+    content: &str,
+    recipients: &[age::x25519::Recipient],
+) -> Result<SaveInfo, PersistenceError> {
+    let metadata = FileMetadata::for_content(content);
+    save_to_recipients_with_metadata(path, content, recipients, true, false, &metadata)
+}
+
+/// Like `save_to_recipients`, with the options of `save_encrypted_with_metadata`.
+/// The file is [MAGIC] [KEY_SOURCE] followed by an age file; age splits
+/// the payload into authenticated frames itself, so large notes need no
+/// chunked variant.
+pub fn save_to_recipients_with_metadata<P: AsRef<Path>>(
+    path: P,
+    content: &str,
+    recipients: &[age::x25519::Recipient],
+    read_only_output: bool,
+    scrub_on_overwrite: bool,
+    metadata: &FileMetadata,
+) -> Result<SaveInfo, PersistenceError> {
+    let encryptor =
+        age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+            .map_err(|e| PersistenceError::Encryption(e.to_string()))?;
+
+    let meta =
+        toml::to_string(metadata).map_err(|e| PersistenceError::Encryption(e.to_string()))?;
+    let meta_len = (meta.len() as u32).to_le_bytes();
+    let parts = [&meta_len[..], meta.as_bytes(), content.as_bytes()];
+
+    let mut header = MAGIC_BYTES.to_vec();
+    header.push(KeySource::Recipients.to_byte());
+    write_atomic_scrub(&path, scrub_on_overwrite, |file| {
+        file.write_all(&header)?;
+        let mut writer = encryptor.wrap_output(&mut *file)?;
+        for part in parts {
+            writer.write_all(part)?;
+        }
+        writer.finish().map(|_| ())
+    })?;
+    finish_save(path, CURRENT_VERSION, read_only_output, None)
+}
+
+/// Marks a freshly saved file read-only if asked and describes it.
+fn finish_save<P: AsRef<Path>>(
+    path: P,
+    version: u8,
+    read_only_output: bool,
+    kdf: Option<KdfParams>,
+) -> Result<SaveInfo, PersistenceError> {
+    // 5. Make Read-Only (Safety)
+    let file_meta = fs::metadata(&path)?;
+    if read_only_output {
+        let mut perms = file_meta.permissions();
+        perms.set_readonly(true);
+        fs::set_permissions(&path, perms)?;
+    }
+
+    Ok(SaveInfo {
+        path: path.as_ref().to_path_buf(),
+        bytes_written: file_meta.len(),
+        version,
+        kdf,
+    })
+}
+
+/// Opens an existing file for writing even if it is read-only, restoring
+/// its permissions straight away. Returns `None` if there is no file.
+fn open_for_scrub(path: &Path) -> std::io::Result<Option<File>> {
+    let perms = match fs::metadata(path) {
+        Ok(m) => m.permissions(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if !perms.readonly() {
+        return OpenOptions::new().write(true).open(path).map(Some);
+    }
+
+    let mut writable = perms.clone();
+    #[allow(clippy::permissions_set_readonly_false)]
+    writable.set_readonly(false);
+    fs::set_permissions(path, writable)?;
+    let file = OpenOptions::new().write(true).open(path);
+    fs::set_permissions(path, perms)?;
+    file.map(Some)
+}
+
+/// Overwrites the whole of `file` with random bytes and flushes it to disk.
+fn overwrite_random(file: &mut File) -> std::io::Result<()> {
+    let len = file.metadata()?.len() as usize;
+    file.seek(SeekFrom::Start(0))?;
+
+    let mut chunk = [0u8; 4096];
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(chunk.len());
+        OsRng.fill_bytes(&mut chunk[..n]);
+        file.write_all(&chunk[..n])?;
+        remaining -= n;
+    }
+    file.sync_all()
+}
+
+/// A random, non-descriptive file name: 16 hex characters plus `.amnesio`.
+pub fn random_filename() -> String {
+    let mut bytes = [0u8; 8];
+    OsRng.fill_bytes(&mut bytes);
+    let name: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}.amnesio", name)
+}
+
+/// Derives the file key from a password or keyfile and the file's salt with
+/// Argon2id. Keyfiles go through the same stretching as passwords.
+fn derive_key(
+    credential: Credential,
+    salt_bytes: &[u8],
+) -> Result<[u8; KEY_LEN], PersistenceError> {
     let mut key_bytes = [0u8; KEY_LEN];
-    let salt = SaltString::encode_b64(&salt_bytes)
+    let salt = SaltString::encode_b64(salt_bytes)
         .map_err(|e| PersistenceError::Encryption(e.to_string()))?;
 
     // We use default params for simplicity, but it's significantly stronger than PBKDF2
     let argon2 = Argon2::default();
     argon2
         .hash_password_into(
-            password.as_bytes(),
+            credential.as_bytes(),
             salt.as_str().as_bytes(),
             &mut key_bytes,
         )
         .map_err(|e| PersistenceError::Encryption(e.to_string()))?;
+    Ok(key_bytes)
+}
+
+/// Writes `parts` as one AEAD message (v6).
+fn save_single<P: AsRef<Path>>(
+    path: P,
+    parts: &[&[u8]],
+    credential: Credential,
+    scrub: bool,
+) -> Result<(), PersistenceError> {
+    // 1. Generate Salt and Nonce
+    let mut salt_bytes = [0u8; SALT_LEN];
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt_bytes);
+    OsRng.fill_bytes(&mut nonce_bytes);
 
-    let cipher_key = Key::from_slice(&key_bytes);
-    let cipher = ChaCha20Poly1305::new(cipher_key);
+    // 2. Derive Key using Argon2id
+    let mut key_bytes = derive_key(credential, &salt_bytes)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key_bytes));
+    key_bytes.zeroize();
     let nonce = Nonce::from_slice(&nonce_bytes);
 
     // 3. Encrypt
+    let mut payload = parts.concat();
+
+    // The header is bound to the ciphertext, so tampering with the version,
+    // key source, salt or nonce fails authentication.
+    let mut header = Vec::with_capacity(MAGIC_BYTES.len() + 1 + SALT_LEN + NONCE_LEN);
+    header.extend_from_slice(MAGIC_BYTES);
+    header.push(credential.source().to_byte());
+    header.extend_from_slice(&salt_bytes);
+    header.extend_from_slice(&nonce_bytes);
+
     let ciphertext = cipher
-        .encrypt(nonce, content.as_bytes())
-        .map_err(|_| PersistenceError::Encryption("Encryption failed".into()))?;
+        .encrypt(
+            nonce,
+            Payload {
+                msg: &payload,
+                aad: &header,
+            },
+        )
+        .map_err(|_| PersistenceError::Encryption("Encryption failed".into()));
+    payload.zeroize();
+    let ciphertext = ciphertext?;
 
-    // 4. Write to File: [MAGIC] [SALT_BYTES] [NONCE] [CIPHERTEXT]
-    let mut file = File::create(&path)?;
-    file.write_all(MAGIC_BYTES)?;
-    file.write_all(&salt_bytes)?;
-    file.write_all(&nonce_bytes)?;
-    file.write_all(&ciphertext)?;
+    // 4. Write to File: [MAGIC] [KEY_SOURCE] [SALT_BYTES] [NONCE] [CIPHERTEXT]
+    write_atomic_scrub(&path, scrub, |file| {
+        file.write_all(&header)?;
+        file.write_all(&ciphertext)
+    })?;
+    Ok(())
+}
 
-    // 5. Make Read-Only (Safety)
-    let mut perms = file.metadata()?.permissions();
-    perms.set_readonly(true);
-    file.set_permissions(perms)?;
+/// Nonce for frame `counter` of a chunked file. The last-frame flag makes
+/// truncation at a frame boundary fail authentication.
+fn chunk_nonce(prefix: &[u8], counter: u32, last: bool) -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_LEN - 1] = last as u8;
+    nonce
+}
+
+/// Writes `parts` as a sequence of AEAD frames (v7), encrypting one chunk
+/// at a time in place: [MAGIC] [KEY_SOURCE] [SALT] [NONCE PREFIX] then per
+/// frame [CIPHERTEXT (up to 64KB)] [TAG]. Every frame authenticates the header.
+fn save_chunked<P: AsRef<Path>>(
+    path: P,
+    parts: &[&[u8]],
+    credential: Credential,
+    scrub: bool,
+) -> Result<(), PersistenceError> {
+    let mut salt_bytes = [0u8; SALT_LEN];
+    let mut prefix = [0u8; NONCE_PREFIX_LEN];
+    OsRng.fill_bytes(&mut salt_bytes);
+    OsRng.fill_bytes(&mut prefix);
 
-    // Zeroize key
+    let mut key_bytes = derive_key(credential, &salt_bytes)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key_bytes));
     key_bytes.zeroize();
 
+    let mut header = Vec::with_capacity(MAGIC_CHUNKED.len() + 1 + SALT_LEN + NONCE_PREFIX_LEN);
+    header.extend_from_slice(MAGIC_CHUNKED);
+    header.push(credential.source().to_byte());
+    header.extend_from_slice(&salt_bytes);
+    header.extend_from_slice(&prefix);
+
+    let total: usize = parts.iter().map(|p| p.len()).sum();
+    let frames = total.div_ceil(CHUNK_LEN).max(1);
+    let mut chunk = vec![0u8; CHUNK_LEN];
+
+    let result = write_atomic_scrub(&path, scrub, |file| {
+        file.write_all(&header)?;
+        for i in 0..frames {
+            let start = i * CHUNK_LEN;
+            let len = (total - start).min(CHUNK_LEN);
+            copy_range(parts, start, &mut chunk[..len]);
+
+            let nonce = chunk_nonce(&prefix, i as u32, i + 1 == frames);
+            let tag = cipher
+                .encrypt_in_place_detached(Nonce::from_slice(&nonce), &header, &mut chunk[..len])
+                .map_err(|_| std::io::Error::other("Encryption failed"))?;
+            file.write_all(&chunk[..len])?;
+            file.write_all(&tag)?;
+        }
+        Ok(())
+    });
+    chunk.zeroize();
+    result.map_err(PersistenceError::from)
+}
+
+/// Copies `out.len()` bytes starting at `start` of the concatenation of `parts`.
+fn copy_range(parts: &[&[u8]], mut start: usize, out: &mut [u8]) {
+    let mut filled = 0;
+    for part in parts {
+        if start >= part.len() {
+            start -= part.len();
+            continue;
+        }
+        let n = (part.len() - start).min(out.len() - filled);
+        out[filled..filled + n].copy_from_slice(&part[start..start + n]);
+        filled += n;
+        start = 0;
+        if filled == out.len() {
+            break;
+        }
+    }
+}
+
+/// Writes a file via a temporary sibling that is fsynced and then renamed
+/// over `path`, so an interrupted write never leaves a truncated file.
+/// If `write` fails, the temporary file is removed and `path` is untouched.
+pub fn write_atomic<P, F>(path: P, write: F) -> std::io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut File) -> std::io::Result<()>,
+{
+    write_atomic_scrub(path, false, write)
+}
+
+/// Like `write_atomic`. With `scrub`, the file being replaced is opened
+/// before the rename and its old bytes are overwritten with random data and
+/// synced afterwards, so the previous ciphertext doesn't linger on disk.
+/// If the write fails the old file is left untouched.
+pub fn write_atomic_scrub<P, F>(path: P, scrub: bool, write: F) -> std::io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut File) -> std::io::Result<()>,
+{
+    let path = path.as_ref();
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "No file name"))?;
+
+    let mut suffix = [0u8; 8];
+    OsRng.fill_bytes(&mut suffix);
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let tmp_path = dir.join(format!(".{}.{}.tmp", name.to_string_lossy(), suffix));
+
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        write(&mut file)?;
+        file.sync_all()?;
+        drop(file);
+        let old = if scrub { open_for_scrub(path)? } else { None };
+        fs::rename(&tmp_path, path)?;
+        match old {
+            Some(mut old) => overwrite_random(&mut old),
+            None => Ok(()),
+        }
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+        return result;
+    }
+
+    // Persist the rename itself.
+    #[cfg(unix)]
+    if let Ok(d) = File::open(dir) {
+        let _ = d.sync_all();
+    }
+
     Ok(())
 }
 
 pub fn load_encrypted<P: AsRef<Path>>(path: P, password: &str) -> Result<String, PersistenceError> {
+    load_encrypted_with_metadata(path, password).map(|(content, _)| content)
+}
+
+/// Like `load_encrypted`, but content that isn't valid UTF-8 is loaded with
+/// the bad bytes replaced by U+FFFD instead of failing. The flag is true if
+/// anything was replaced, so the caller can warn that the note is damaged.
+pub fn load_encrypted_lossy<P: AsRef<Path>>(
+    path: P,
+    password: &str,
+) -> Result<(String, bool), PersistenceError> {
+    load_with_credential_lossy(path, Credential::Password(password))
+        .map(|(content, _, replaced)| (content, replaced))
+}
+
+/// Like `load_encrypted`, also returning the metadata block.
+/// Files from before metadata existed (v2) have none.
+pub fn load_encrypted_with_metadata<P: AsRef<Path>>(
+    path: P,
+    password: &str,
+) -> Result<(String, Option<FileMetadata>), PersistenceError> {
+    load_with_credential(path, Credential::Password(password))
+}
+
+/// Like `load_encrypted_with_metadata`, for either kind of credential.
+/// Fails with `WrongKeySource` if the file was locked with the other kind.
+pub fn load_with_credential<P: AsRef<Path>>(
+    path: P,
+    credential: Credential,
+) -> Result<(String, Option<FileMetadata>), PersistenceError> {
+    let (version, payload) = decrypt_file(path, credential)?;
+    decode_payload(version, payload, false).map(|(content, metadata, _)| (content, metadata))
+}
+
+/// `load_with_credential` with the UTF-8 handling of `load_encrypted_lossy`.
+pub fn load_with_credential_lossy<P: AsRef<Path>>(
+    path: P,
+    credential: Credential,
+) -> Result<(String, Option<FileMetadata>, bool), PersistenceError> {
+    let (version, payload) = decrypt_file(path, credential)?;
+    decode_payload(version, payload, true)
+}
+
+/// Opens a file saved with `save_to_recipients` using the private keys in
+/// an age identity file. Fails with `WrongKeySource` for other files.
+pub fn load_with_identity<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    identity_file: Q,
+) -> Result<(String, Option<FileMetadata>), PersistenceError> {
+    let (version, payload) = decrypt_with_identity(path, identity_file)?;
+    decode_payload(version, payload, false).map(|(content, metadata, _)| (content, metadata))
+}
+
+/// `load_with_identity` with the UTF-8 handling of `load_encrypted_lossy`.
+pub fn load_with_identity_lossy<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    identity_file: Q,
+) -> Result<(String, Option<FileMetadata>, bool), PersistenceError> {
+    let (version, payload) = decrypt_with_identity(path, identity_file)?;
+    decode_payload(version, payload, true)
+}
+
+/// Decrypts an age-recipients file, returning its version and raw plaintext.
+fn decrypt_with_identity<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    identity_file: Q,
+) -> Result<(u8, Vec<u8>), PersistenceError> {
     let mut file = File::open(path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
+    let (version, header) = read_header(&mut file)?;
+    let source = header_key_source(&header)?;
+    if source != KeySource::Recipients {
+        return Err(PersistenceError::WrongKeySource(source));
+    }
 
-    if buffer.len() < MAGIC_BYTES.len() + SALT_LEN + NONCE_LEN {
+    let identity_file = identity_file.as_ref().to_string_lossy().into_owned();
+    let identities = age::IdentityFile::from_file(identity_file)?
+        .into_identities()
+        .map_err(|e| PersistenceError::InvalidKeyfile(e.to_string()))?;
+    let reader = age::Decryptor::new_buffered(BufReader::new(file))
+        .map_err(|_| PersistenceError::InvalidFileFormat)?
+        .decrypt(identities.iter().map(|i| i.as_ref() as &dyn age::Identity))
+        .map_err(|e| match e {
+            age::DecryptError::Io(e) => PersistenceError::Io(e),
+            _ => PersistenceError::DecryptionFailed,
+        })?;
+    // A tampered payload surfaces as a read error partway through.
+    let payload = crate::secret::read_all_zeroizing(reader)
+        .map_err(|_| PersistenceError::DecryptionFailed)?;
+    Ok((version, payload))
+}
+
+/// Splits decrypted plaintext into content and, from v3 on, its metadata.
+/// With `lossy`, invalid UTF-8 is replaced rather than an error, and the
+/// returned flag says whether that happened.
+fn decode_payload(
+    version: u8,
+    mut payload: Vec<u8>,
+    lossy: bool,
+) -> Result<(String, Option<FileMetadata>, bool), PersistenceError> {
+    let (metadata, content_bytes) = if version >= 3 {
+        let split = split_metadata(&payload);
+        payload.zeroize();
+        split?
+    } else {
+        (None, payload)
+    };
+
+    match String::from_utf8(content_bytes) {
+        Ok(plaintext) => Ok((plaintext, metadata, false)),
+        Err(e) => {
+            let mut bytes = e.into_bytes();
+            let result = if lossy {
+                Ok((String::from_utf8_lossy(&bytes).into_owned(), metadata, true))
+            } else {
+                Err(PersistenceError::Encryption(
+                    "Decrypted content is not valid UTF-8".into(),
+                ))
+            };
+            bytes.zeroize();
+            result
+        }
+    }
+}
+
+/// Splits a v3 payload into its metadata block and a copy of the content.
+fn split_metadata(payload: &[u8]) -> Result<(Option<FileMetadata>, Vec<u8>), PersistenceError> {
+    if payload.len() < 4 {
         return Err(PersistenceError::InvalidFileFormat);
     }
+    let meta_len = u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]) as usize;
+    let meta_bytes = payload
+        .get(4..4 + meta_len)
+        .ok_or(PersistenceError::InvalidFileFormat)?;
+    let metadata = std::str::from_utf8(meta_bytes)
+        .ok()
+        .and_then(|m| toml::from_str::<FileMetadata>(m).ok())
+        .ok_or(PersistenceError::InvalidFileFormat)?;
+    Ok((Some(metadata), payload[4 + meta_len..].to_vec()))
+}
+
+/// Checks that `password` opens the file and its authentication tag is intact,
+/// without handing back the plaintext. Returns `DecryptionFailed` on mismatch.
+pub fn verify<P: AsRef<Path>>(path: P, password: &str) -> Result<(), PersistenceError> {
+    let (_, mut plaintext_bytes) = decrypt_file(path, Credential::Password(password))?;
+    plaintext_bytes.zeroize();
+    Ok(())
+}
+
+/// Reads the format version from a file's header without decrypting it.
+pub fn file_version<P: AsRef<Path>>(path: P) -> Result<u8, PersistenceError> {
+    let mut header = [0u8; 8];
+    File::open(path)?
+        .read_exact(&mut header)
+        .map_err(|_| PersistenceError::InvalidFileFormat)?;
+    version_from_magic(&header)
+}
+
+/// Reads which kind of key a file was locked with, without decrypting it.
+pub fn key_source<P: AsRef<Path>>(path: P) -> Result<KeySource, PersistenceError> {
+    let (_, header) = read_header(&mut File::open(path)?)?;
+    header_key_source(&header)
+}
+
+/// How a file was encrypted, as far as its unencrypted header tells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInfo {
+    pub version: u8,
+    pub key_source: KeySource,
+    pub cipher: &'static str,
+    /// An assumption, not read from the file: no format records its Argon2
+    /// parameters, so this is what this build derives keys with
+    /// (`KdfParams::current`). None for age recipients.
+    pub assumed_kdf: Option<KdfParams>,
+    pub chunked: bool,              // Streamed in 64KB AEAD frames (v5, v7)
+    pub header_authenticated: bool, // Header bound to the ciphertext as AAD
+}
+
+/// Describes how a file was encrypted without needing its password.
+pub fn file_info<P: AsRef<Path>>(path: P) -> Result<FileInfo, PersistenceError> {
+    let (version, header) = read_header(&mut File::open(path)?)?;
+    let key_source = header_key_source(&header)?;
+    let recipients = key_source == KeySource::Recipients;
+    Ok(FileInfo {
+        version,
+        key_source,
+        cipher: if recipients {
+            "age (X25519, ChaCha20-Poly1305)"
+        } else {
+            "ChaCha20-Poly1305"
+        },
+        assumed_kdf: (!recipients).then(KdfParams::current),
+        chunked: version == 5 || version == CHUNKED_VERSION,
+        header_authenticated: version >= 4 && !recipients,
+    })
+}
+
+/// Reads the magic and, from v6 on, the key source byte that follows it.
+fn read_header(file: &mut File) -> Result<(u8, Vec<u8>), PersistenceError> {
+    let mut header = vec![0u8; MAGIC_BYTES.len()];
+    file.read_exact(&mut header)
+        .map_err(|_| PersistenceError::InvalidFileFormat)?;
+    let version = version_from_magic(&header)?;
+    if version >= KEY_SOURCE_VERSION {
+        let mut source = [0u8; 1];
+        file.read_exact(&mut source)
+            .map_err(|_| PersistenceError::InvalidFileFormat)?;
+        header.push(source[0]);
+    }
+    Ok((version, header))
+}
 
-    // 1. Verify Magic
-    if &buffer[0..MAGIC_BYTES.len()] != MAGIC_BYTES.as_slice() {
+fn header_key_source(header: &[u8]) -> Result<KeySource, PersistenceError> {
+    match header.get(MAGIC_BYTES.len()) {
+        Some(&byte) => KeySource::from_byte(byte),
+        None => Ok(KeySource::Password),
+    }
+}
+
+/// Maps a magic header to a readable format version. Every version this
+/// build can decrypt must be listed here.
+/// Version 1 was never released in a readable form, so it is rejected.
+fn version_from_magic(magic: &[u8]) -> Result<u8, PersistenceError> {
+    match magic {
+        m if m == MAGIC_CHUNKED.as_slice() => Ok(CHUNKED_VERSION),
+        m if m == MAGIC_BYTES.as_slice() => Ok(CURRENT_VERSION),
+        m if m == MAGIC_V5.as_slice() => Ok(5),
+        m if m == MAGIC_V4.as_slice() => Ok(4),
+        m if m == MAGIC_V3.as_slice() => Ok(3),
+        m if m == MAGIC_V2.as_slice() => Ok(2),
+        [prefix @ .., digit] if prefix == MAGIC_PREFIX.as_slice() && digit.is_ascii_digit() => {
+            Err(PersistenceError::UnsupportedVersion(digit - b'0'))
+        }
+        _ => Err(PersistenceError::InvalidFileFormat),
+    }
+}
+
+/// Re-saves a file from an older format version in the current one.
+/// Returns false if it was already current. Keeps the read-only state.
+pub fn migrate<P: AsRef<Path>>(path: P, password: &str) -> Result<bool, PersistenceError> {
+    let path = path.as_ref();
+    if file_version(path)? >= CURRENT_VERSION {
+        return Ok(false);
+    }
+    rechange_password(path, password, password)?;
+    Ok(true)
+}
+
+/// Reads and decrypts a file, returning its format version and the raw
+/// plaintext bytes (for v3 and later, the metadata block plus content).
+fn decrypt_file<P: AsRef<Path>>(
+    path: P,
+    credential: Credential,
+) -> Result<(u8, Vec<u8>), PersistenceError> {
+    let mut file = File::open(path)?;
+
+    // 1. Verify Magic and key source
+    let (version, header) = read_header(&mut file)?;
+    let source = header_key_source(&header)?;
+    if source != credential.source() {
+        return Err(PersistenceError::WrongKeySource(source));
+    }
+    if version == 5 || version == CHUNKED_VERSION {
+        return decrypt_chunked(file, header, credential).map(|plaintext| (version, plaintext));
+    }
+
+    let salt_offset = header.len();
+    let mut buffer = header;
+    file.read_to_end(&mut buffer)?;
+
+    // Every ciphertext carries a tag, so anything shorter is truncated
+    // rather than locked with another password.
+    if buffer.len() < salt_offset + SALT_LEN + NONCE_LEN + TAG_LEN {
         return Err(PersistenceError::InvalidFileFormat);
     }
 
-    let salt_offset = MAGIC_BYTES.len();
     let nonce_offset = salt_offset + SALT_LEN;
     let ciphertext_offset = nonce_offset + NONCE_LEN;
 
@@ -117,32 +937,127 @@ pub fn load_encrypted<P: AsRef<Path>>(path: P, password: &str) -> Result<String,
     let ciphertext = &buffer[ciphertext_offset..];
 
     // 2. Derive Key
-    let mut key_bytes = [0u8; KEY_LEN];
-    let salt = SaltString::encode_b64(salt_bytes)
-        .map_err(|e| PersistenceError::Encryption(e.to_string()))?;
+    let mut key_bytes = derive_key(credential, salt_bytes)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key_bytes));
+    key_bytes.zeroize();
+    let nonce = Nonce::from_slice(nonce_bytes);
 
-    let argon2 = Argon2::default();
-    argon2
-        .hash_password_into(
-            password.as_bytes(),
-            salt.as_str().as_bytes(),
-            &mut key_bytes,
+    // 3. Decrypt. Before v4 the header was not authenticated.
+    let aad = if version >= 4 {
+        &buffer[..ciphertext_offset]
+    } else {
+        &[]
+    };
+    let plaintext = cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
         )
-        .map_err(|e| PersistenceError::Encryption(e.to_string()))?;
+        .map_err(|_| PersistenceError::DecryptionFailed)?;
 
-    let cipher_key = Key::from_slice(&key_bytes);
-    let cipher = ChaCha20Poly1305::new(cipher_key);
-    let nonce = Nonce::from_slice(nonce_bytes);
+    Ok((version, plaintext))
+}
 
-    // 3. Decrypt
-    let plaintext_bytes = cipher
-        .decrypt(nonce, ciphertext)
-        .map_err(|_| PersistenceError::DecryptionFailed)?;
+/// Decrypts the frames of a v5 or v7 file, positioned just after `header`
+/// (the magic and any key source byte).
+/// Each frame is decrypted in place inside the output, which is sized up
+/// front so it never reallocates and leaves stray plaintext copies.
+fn decrypt_chunked(
+    mut file: File,
+    mut header: Vec<u8>,
+    credential: Credential,
+) -> Result<Vec<u8>, PersistenceError> {
+    let salt_offset = header.len();
+    header.resize(salt_offset + SALT_LEN + NONCE_PREFIX_LEN, 0);
+    file.read_exact(&mut header[salt_offset..])
+        .map_err(|_| PersistenceError::InvalidFileFormat)?;
+    let salt_bytes = &header[salt_offset..salt_offset + SALT_LEN];
+    let prefix = &header[salt_offset + SALT_LEN..];
 
-    let plaintext = String::from_utf8(plaintext_bytes)
-        .map_err(|_| PersistenceError::Encryption("Decrypted content is not valid UTF-8".into()))?;
+    // Frame boundaries follow from the file length: all frames are full
+    // except the last, which must at least hold a tag.
+    let body_len = (file.metadata()?.len() as usize).saturating_sub(header.len());
+    let frame_len = CHUNK_LEN + TAG_LEN;
+    let frames = body_len.div_ceil(frame_len).max(1);
+    let last_len = body_len - (frames - 1) * frame_len;
+    if last_len < TAG_LEN {
+        return Err(PersistenceError::InvalidFileFormat);
+    }
 
+    let mut key_bytes = derive_key(credential, salt_bytes)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key_bytes));
     key_bytes.zeroize();
 
-    Ok(plaintext)
+    let mut out: Vec<u8> = Vec::with_capacity(body_len - frames * TAG_LEN);
+    for i in 0..frames {
+        let last = i + 1 == frames;
+        let len = if last { last_len } else { frame_len } - TAG_LEN;
+        let start = out.len();
+        out.resize(start + len, 0);
+        let mut tag = [0u8; TAG_LEN];
+        let read = file
+            .read_exact(&mut out[start..])
+            .and_then(|_| file.read_exact(&mut tag));
+        let nonce = chunk_nonce(prefix, i as u32, last);
+        let result = match read {
+            Ok(()) => cipher
+                .decrypt_in_place_detached(
+                    Nonce::from_slice(&nonce),
+                    &header,
+                    &mut out[start..],
+                    Tag::from_slice(&tag),
+                )
+                // Once a frame has authenticated the key is known to be
+                // right, so a later failure means the file was damaged.
+                .map_err(|_| match i {
+                    0 => PersistenceError::DecryptionFailed,
+                    _ => PersistenceError::InvalidFileFormat,
+                }),
+            Err(e) => Err(PersistenceError::Io(e)),
+        };
+        if let Err(e) = result {
+            out.zeroize();
+            return Err(e);
+        }
+    }
+    Ok(out)
+}
+
+/// Re-encrypts a file under a new password with a fresh salt and nonce.
+/// The plaintext only ever exists in memory and is zeroized afterwards.
+/// The file keeps its current read-only state.
+pub fn rechange_password<P: AsRef<Path>>(
+    path: P,
+    old_password: &str,
+    new_password: &str,
+) -> Result<(), PersistenceError> {
+    let path = path.as_ref();
+    let read_only = fs::metadata(path)?.permissions().readonly();
+    let (mut content, metadata) = load_encrypted_with_metadata(path, old_password)?;
+    let metadata = metadata.unwrap_or_else(|| FileMetadata::for_content(&content));
+    let result =
+        save_encrypted_with_metadata(path, &content, new_password, read_only, false, &metadata);
+    content.zeroize();
+    result.map(|_| ())
+}
+
+/// Overwrites a file with random bytes, flushes it to disk and deletes it.
+pub fn shred_file<P: AsRef<Path>>(path: P) -> Result<(), PersistenceError> {
+    let path = path.as_ref();
+
+    // Saved files are read-only, so lift that first.
+    let mut perms = fs::metadata(path)?.permissions();
+    #[allow(clippy::permissions_set_readonly_false)]
+    perms.set_readonly(false);
+    fs::set_permissions(path, perms)?;
+
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    overwrite_random(&mut file)?;
+    drop(file);
+
+    fs::remove_file(path)?;
+    Ok(())
 }
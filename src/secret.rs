@@ -0,0 +1,114 @@
+use std::io::{self, Read};
+use zeroize::Zeroize;
+
+/// Maximum number of bytes a `SecretBuffer` can hold.
+pub const SECRET_CAPACITY: usize = 256;
+
+/// A fixed-capacity text buffer for secrets such as passwords.
+/// The backing storage never reallocates, so no partial copies are left
+/// behind on the heap, and removed bytes are overwritten immediately.
+pub struct SecretBuffer {
+    data: [u8; SECRET_CAPACITY],
+    len: usize,
+}
+
+impl SecretBuffer {
+    pub fn new() -> Self {
+        Self {
+            data: [0u8; SECRET_CAPACITY],
+            len: 0,
+        }
+    }
+
+    /// Appends a character. Returns false if the buffer is full.
+    pub fn push(&mut self, ch: char) -> bool {
+        let ch_len = ch.len_utf8();
+        if self.len + ch_len > SECRET_CAPACITY {
+            return false;
+        }
+        ch.encode_utf8(&mut self.data[self.len..self.len + ch_len]);
+        self.len += ch_len;
+        true
+    }
+
+    /// Removes the last character and overwrites its bytes.
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.as_str().chars().next_back()?;
+        let new_len = self.len - ch.len_utf8();
+        self.data[new_len..self.len].zeroize();
+        self.len = new_len;
+        Some(ch)
+    }
+
+    /// Wipes the whole buffer.
+    pub fn clear(&mut self) {
+        self.data.zeroize();
+        self.len = 0;
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole UTF-8 encoded chars are ever written.
+        std::str::from_utf8(&self.data[..self.len]).unwrap_or("")
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Default for SecretBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SecretBuffer {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
+/// Reads everything from `reader`, zeroing every intermediate allocation as
+/// the result grows. The caller is responsible for zeroizing the result.
+pub fn read_all_zeroizing<R: Read>(mut reader: R) -> io::Result<Vec<u8>> {
+    let mut out: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                chunk.zeroize();
+                out.zeroize();
+                return Err(e);
+            }
+        };
+        if out.len() + n > out.capacity() {
+            // Grow by hand so the old allocation can be wiped.
+            let mut grown = Vec::with_capacity((out.len() + n).max(out.capacity() * 2));
+            grown.extend_from_slice(&out);
+            out.zeroize();
+            out = grown;
+        }
+        out.extend_from_slice(&chunk[..n]);
+    }
+    chunk.zeroize();
+    Ok(out)
+}
+
+/// Converts bytes to a `String` without leaving an unzeroed copy behind.
+/// Invalid UTF-8 is replaced lossily.
+pub fn into_string_zeroizing(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|e| {
+        let mut bytes = e.into_bytes();
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+        bytes.zeroize();
+        text
+    })
+}
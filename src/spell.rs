@@ -0,0 +1,82 @@
+//! Offline spell checking against a plain word list, one word per line
+//! (such as `/usr/share/dict/words`). Nothing leaves the machine.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The word list checked when `dictionary` isn't set in the config.
+pub const DEFAULT_DICTIONARY: &str = "/usr/share/dict/words";
+
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(fs::read_to_string(path)?.lines().collect())
+    }
+
+    /// Returns true if `word` is in the list as written or in lowercase, so
+    /// "Paris" matches "Paris" and "The" matches "the".
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(word) || self.words.contains(&word.to_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Whether `word` should be flagged. Single letters and anything with
+    /// digits (versions, ids) are never checked.
+    pub fn is_misspelled(&self, word: &str) -> bool {
+        word.chars().count() > 1
+            && word.chars().all(|c| c.is_alphabetic() || c == '\'')
+            && !self.contains(word)
+            && !word
+                .strip_suffix("'s")
+                .is_some_and(|stem| self.contains(stem))
+    }
+}
+
+impl<'a> FromIterator<&'a str> for Dictionary {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let words = iter
+            .into_iter()
+            .map(str::trim)
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self { words }
+    }
+}
+
+/// Byte ranges of the words in `text`: runs of letters and digits, with
+/// apostrophes allowed inside a word ("don't").
+pub fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let inner_apostrophe = c == '\''
+            && start.is_some()
+            && chars
+                .peek()
+                .is_some_and(|&(_, next)| next.is_alphanumeric());
+        if c.is_alphanumeric() || inner_apostrophe {
+            start.get_or_insert(i);
+        } else if let Some(s) = start.take() {
+            ranges.push((s, i));
+        }
+    }
+    if let Some(s) = start {
+        ranges.push((s, text.len()));
+    }
+    ranges
+}
@@ -1,40 +1,48 @@
 use sha2::{Digest, Sha256};
-use std::process::Command;
 use zeroize::Zeroize;
 
 /// A static variable to leverage ASLR in key derivation.
 static ASLR_ANCHOR: u8 = 0xAA;
 
-/// Derives a 32-byte key using system data, ASLR, and startup randomness.
-/// This makes it difficult to reproduce the key from a memory dump.
+/// Derives a fresh 32-byte key from system data, ASLR and new randomness.
+/// Every call returns a different key: a buffer keeps the key it was
+/// created with, and new tabs and key rotation rely on getting a new one.
 pub fn derive_key() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    if getrandom::getrandom(&mut seed).is_err() {
+        fallback_seed(&mut seed);
+    }
+    let key = derive_key_with_seed(&seed);
+    seed.zeroize();
+    key
+}
+
+/// Derives a key from `seed` mixed with system data and an ASLR address.
+/// The same seed gives the same key for the life of the process. Another
+/// process can't reproduce it even with the seed, because the address of
+/// the ASLR anchor changes with every run, so a key recovered from one
+/// session's memory dump is useless for any other.
+pub fn derive_key_with_seed(seed: &[u8; 32]) -> [u8; 32] {
     let mut entropy = Vec::new();
 
     // 1. System Hostname
-    if let Ok(output) = Command::new("hostname").output() {
-        entropy.extend_from_slice(&output.stdout);
-    }
+    capture_hostname(&mut entropy);
 
     // 2. Kernel Version / System Info
-    if let Ok(output) = Command::new("uname").arg("-a").output() {
-        entropy.extend_from_slice(&output.stdout);
-    }
+    capture_system_info(&mut entropy);
 
-    // 3. Boot Time (macOS specific, fallback to 0 if fails)
-    let boot_time = capture_boot_time();
-    entropy.extend_from_slice(&boot_time.to_le_bytes());
+    // 3. Boot Time, or the process id where it is unavailable
+    match capture_boot_time() {
+        Some(boot_time) => entropy.extend_from_slice(&boot_time.to_le_bytes()),
+        None => entropy.extend_from_slice(&std::process::id().to_le_bytes()),
+    }
 
     // 4. ASLR-based address of a static variable
     let aslr_addr = &ASLR_ANCHOR as *const u8 as usize;
     entropy.extend_from_slice(&aslr_addr.to_le_bytes());
 
-    // 5. Ephemeral Startup Randomness
-    let mut startup_random = [0u8; 32];
-    if getrandom::getrandom(&mut startup_random).is_err() {
-        // Fallback to some "random" looking static data if getrandom fails (unlikely)
-        startup_random.copy_from_slice(b"AMNESIA_STEALTH_FALLBACK_RANDOM_");
-    }
-    entropy.extend_from_slice(&startup_random);
+    // 5. The caller's seed
+    entropy.extend_from_slice(seed);
 
     // 6. Creative Shuffling (simple but non-obvious)
     creative_shuffle(&mut entropy);
@@ -49,29 +57,82 @@ pub fn derive_key() -> [u8; 32] {
 
     // Cleanup entropy
     entropy.zeroize();
-    startup_random.zeroize();
 
     key
 }
 
-fn capture_boot_time() -> u64 {
-    #[cfg(target_os = "macos")]
+/// Appends the host name, read directly rather than via the `hostname` binary.
+fn capture_hostname(entropy: &mut Vec<u8>) {
+    #[cfg(unix)]
+    {
+        let mut name = [0u8; 256];
+        let rc = unsafe { libc::gethostname(name.as_mut_ptr() as *mut libc::c_char, name.len()) };
+        if rc == 0 {
+            let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            entropy.extend_from_slice(&name[..len]);
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        if let Some(name) = std::env::var_os("COMPUTERNAME") {
+            entropy.extend_from_slice(name.to_string_lossy().as_bytes());
+        }
+    }
+}
+
+/// Appends the same fields `uname -a` prints, without spawning it.
+fn capture_system_info(entropy: &mut Vec<u8>) {
+    #[cfg(unix)]
     {
-        // On macOS, sysctl kern.boottime returns a struct timeval
-        if let Ok(output) = Command::new("sysctl")
-            .arg("-n")
-            .arg("kern.boottime")
-            .output()
-        {
-            let s = String::from_utf8_lossy(&output.stdout);
-            // Example output: { sec = 1770452418, usec = 373454 } Sat Feb  7 09:20:18 2026
-            // We'll just take the whole string as entropy for simplicity and creativity.
-            let mut hasher = Sha256::new();
-            hasher.update(s.as_bytes());
-            let result = hasher.finalize();
-            let mut bytes = [0u8; 8];
-            bytes.copy_from_slice(&result[..8]);
-            return u64::from_le_bytes(bytes);
+        let mut info: libc::utsname = unsafe { std::mem::zeroed() };
+        if unsafe { libc::uname(&mut info) } == 0 {
+            for field in [
+                &info.sysname[..],
+                &info.nodename[..],
+                &info.release[..],
+                &info.version[..],
+                &info.machine[..],
+            ] {
+                let len = field.iter().position(|&c| c == 0).unwrap_or(field.len());
+                entropy.extend(field[..len].iter().map(|&c| c as u8));
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        entropy.extend_from_slice(std::env::consts::OS.as_bytes());
+        entropy.extend_from_slice(std::env::consts::ARCH.as_bytes());
+    }
+}
+
+/// System boot time in seconds since the Unix epoch, if the platform exposes it.
+fn capture_boot_time() -> Option<u64> {
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    {
+        // sysctl kern.boottime returns a struct timeval
+        let mut mib = [libc::CTL_KERN, libc::KERN_BOOTTIME];
+        let mut boot_time: libc::timeval = unsafe { std::mem::zeroed() };
+        let mut size = std::mem::size_of::<libc::timeval>();
+        let rc = unsafe {
+            libc::sysctl(
+                mib.as_mut_ptr(),
+                mib.len() as libc::c_uint,
+                &mut boot_time as *mut libc::timeval as *mut libc::c_void,
+                &mut size,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        if rc == 0 && boot_time.tv_sec > 0 {
+            return Some(boot_time.tv_sec as u64);
         }
     }
 
@@ -80,18 +141,38 @@ fn capture_boot_time() -> u64 {
         // On Linux, use /proc/stat btime
         if let Ok(contents) = std::fs::read_to_string("/proc/stat") {
             for line in contents.lines() {
-                if line.starts_with("btime ") {
-                    return line[6..].trim().parse().unwrap_or(0);
+                if let Some(btime) = line.strip_prefix("btime ") {
+                    return btime.trim().parse().ok();
                 }
             }
         }
     }
 
-    0
+    #[cfg(windows)]
+    {
+        // Uptime in milliseconds, subtracted from the current time
+        let uptime_ms = unsafe { windows_sys::Win32::System::SystemInformation::GetTickCount64() };
+        if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            return now.as_secs().checked_sub(uptime_ms / 1000);
+        }
+    }
+
+    None
+}
+
+/// A seed from the clock and process id, for when getrandom fails (unlikely).
+fn fallback_seed(seed: &mut [u8; 32]) {
+    let mut hasher = Sha256::new();
+    hasher.update(b"AMNESIA_STEALTH_FALLBACK_RANDOM_");
+    if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        hasher.update(now.as_nanos().to_le_bytes());
+    }
+    hasher.update(std::process::id().to_le_bytes());
+    seed.copy_from_slice(&hasher.finalize());
 }
 
 /// A "creative" shuffle to mix entropy bytes in a non-standard way.
-fn creative_shuffle(data: &mut Vec<u8>) {
+fn creative_shuffle(data: &mut [u8]) {
     if data.len() < 2 {
         return;
     }
@@ -1,37 +1,192 @@
-use crate::mem_buffer::MemoryBuffer;
-use crate::persistence;
+use crate::config::LineNumbers;
+use crate::keys::{Action, KeyBindings};
+use crate::mem_buffer::{LockedString, MemoryBuffer};
+use crate::persistence::{self, PersistenceError, MIN_PASSWORD_LEN};
+use crate::secret::SecretBuffer;
+use crate::stealth;
+use argon2::password_hash::{
+    rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+};
+use argon2::Argon2;
+use base64::prelude::{Engine, BASE64_STANDARD};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, Clear, Paragraph},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Gauge, Paragraph},
     Frame,
 };
+use std::collections::HashMap;
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 use zeroize::Zeroize;
 
+/// Shown when a note is opened with invalid UTF-8 replaced by U+FFFD.
+pub const DAMAGED_TEXT_WARNING: &str =
+    "WARNING: damaged text, invalid bytes shown as \u{FFFD} (saving keeps them replaced)";
+
+/// The built-in status bar template.
+pub const DEFAULT_STATUS_FORMAT: &str =
+    " {stealth}{line}:{col} | {mem} | Idle: {idle}/{idle_max}s | TTL: {ttl}";
+
+/// Buffer usage, in percent of its capacity, above which the status bar
+/// warns that the next growth will reallocate.
+pub const CAPACITY_WARN_PERCENT: usize = 90;
+
+/// Most tabs open at once, one per go-to-tab action.
+pub const MAX_TABS: usize = 9;
+
+/// A note in a tab that isn't being shown. The active tab's note lives in
+/// `Editor`'s own fields and is swapped with a `Tab` on switching.
+struct Tab {
+    id: u64, // Stays with the note, unlike its index
+    storage: MemoryBuffer,
+    cursor_position: usize,
+    scroll_offset: usize,
+    h_scroll_offset: u16,
+    saved_generation: Option<u64>,
+}
+
+/// Periodic encrypted saves of the current note (see `set_autosave`).
+struct Autosave {
+    path: PathBuf,
+    interval: Duration,
+    password: SecretBuffer,
+    last: Instant,
+    note: u64, // Id of the note being saved; other tabs are left alone
+    saved_generation: Option<u64>,
+}
+
+/// Colors used by the editor. The defaults suit a dark terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub editor_fg: Color,
+    pub editor_bg: Color,
+    pub status_fg: Color,
+    pub status_bg: Color,
+    pub border: Color,
+    pub popup_border: Color,
+    pub read_only: Color,    // Editor border in read-only mode
+    pub stealth: Color,      // The [STEALTH] status tag
+    pub current_line: Color, // Background of the cursor's line, when highlighted
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            editor_fg: Color::White,
+            editor_bg: Color::Black,
+            status_fg: Color::Black,
+            status_bg: Color::DarkGray,
+            border: Color::DarkGray,
+            popup_border: Color::Cyan,
+            read_only: Color::Red,
+            stealth: Color::Black,
+            current_line: Color::Indexed(236),
+        }
+    }
+}
+
 #[derive(PartialEq)]
 pub enum InputMode {
     Normal,
     EnterPath,
+    ConfirmOverwrite, // The save path exists; waiting for y/n
+    ConfirmClear,     // Clearing a note with unsaved changes; waiting for y/n
     EnterPassword,
+    ConfirmPassword,
+    OpenPath,
+    OpenPassword,
+    InsertFile, // Path of a plaintext file to insert at the cursor
+    GotoLine,
+    Locked, // Idle lock: content hidden until the unlock password is entered
+    Help,
 }
 
 pub struct Editor {
     pub storage: MemoryBuffer,
-    pub cursor_position: usize,
-    pub scroll_offset: u16,
+    pub cursor_position: usize, // Byte offset into the note, on a grapheme boundary
+    selection_anchor: Option<(usize, u64)>, // Other end of the selection, and the generation it's for
+    pub scroll_offset: usize,               // First visible row; can pass u16::MAX in long notes
+    pub h_scroll_offset: u16,
+    saved_generation: Option<u64>, // `storage` generation when last saved or opened
+    tabs: Vec<Tab>,                // The other tabs, in order, without the active one
+    active_tab: usize,
+    note_id: u64,                    // Id of the shown note; see `Tab::id`
+    next_note_id: u64,               // Id for the next new tab
+    close_armed_at: Option<Instant>, // First close-tab press on a non-empty note
     pub last_input: Instant,
     pub idle_timeout: Option<Duration>,
     pub ttl_expiry: Option<Instant>,
+    ttl_start: Instant, // When the TTL clock started, for the gauge
+    pub ttl_extension: Option<Duration>, // How far Ctrl+T pushes back the TTL
+    pub max_ttl_extensions: Option<u32>,
+    pub ttl_extensions: u32,
+    pub key_rotation: Option<Duration>, // Re-key the encrypted buffer this often
+    unlock_hash: Option<String>,        // Argon2 hash of the idle-lock password
+    pub swap_active: bool,              // The system has swap; warn if memory isn't pinned
+    last_key_rotation: Instant,
+    pub theme: Theme,
+    pub highlight_current_line: bool,
+    pub ruler: Option<usize>, // Column (1-based) to draw a vertical ruler at
+    pub key_bindings: KeyBindings,
+    pub status_format: String, // Status bar template, see `format_status`
+    pub reading_wpm: u32,      // Words per minute behind the {read} estimate
     pub show_markdown: bool,
+    pub split_preview: bool, // Raw text on the left, rendered Markdown on the right
     pub read_only: bool,
 
+    pub tab_width: usize,
+    pub wrap: bool,
+    pub show_whitespace: bool, // Draw spaces as · and tabs as →, trailing ones in red
+    pub line_numbers: LineNumbers, // Line-number gutter mode
+    pub redacted: bool,        // Draw the note's text as • (privacy screen)
+    pub spell_check: bool,     // Underline words missing from `dictionary`
+    #[cfg(feature = "spellcheck")]
+    pub dictionary: Option<crate::spell::Dictionary>,
+    #[cfg(feature = "spellcheck")]
+    spell_cache: Option<SpellCache>,
+
+    // Decrypted copy of `storage` for rendering, refreshed only when it changes
+    view_cache: LockedString,
+    display_cache: LockedString, // Laid out for the terminal (tabs expanded, wrapped)
+    view_generation: Option<u64>,
+    display_key: Option<(u64, Option<usize>)>, // Generation and wrap width of `display_cache`
+    word_count: Option<(u64, usize)>,          // Generation and word count of `view_cache`
+    line_index: Vec<usize>,                    // Byte offset of each line in `view_cache`
+    line_index_generation: Option<u64>,
+
     // Save functionality
     pub input_mode: InputMode,
     pub path_buffer: String,
-    pub password_buffer: String,
+    path_candidates: Option<(String, Vec<String>)>, // Tab matches, for the path they were listed for
+    pub goto_buffer: String,                        // Line number typed into the Ctrl+G popup
+    pub password_buffer: SecretBuffer,
+    password_strength: u8, // Score of a new password, updated as it's typed
+    pub confirm_buffer: SecretBuffer, // Second entry of a new password
+    pub show_password: bool, // Render the password in clear in the popup
+    pub read_only_output: bool, // Mark saved files read-only
+    pub scrub_on_overwrite: bool, // Overwrite the replaced file's bytes on save
+    pub crlf_on_save: bool, // Write CRLF line endings; the buffer always holds LF
+    pub keyfile: Option<PathBuf>, // Save and open with this keyfile instead of a password
+    pub recipients: Vec<age::x25519::Recipient>, // Save to these age public keys if any
+    pub identity: Option<PathBuf>, // age identity file for opening recipient files
+    autosave: Option<Autosave>,
+    pub min_password_score: Option<u8>, // Minimum password_score (0-4) to save
     pub status_message: Option<(String, Instant)>, // Message and timestamp
+    pub status_timeout: Duration,       // How long a status message stays up
+    pub max_buffer_bytes: Option<usize>, // Refuse input that would grow the note past this
+    clipboard_armed_at: Option<Instant>, // First copy press, awaiting confirmation
+
+    // Open functionality
+    pub max_load_attempts: Option<u32>, // Destroy the file after this many wrong passwords
+    failed_loads: HashMap<PathBuf, u32>, // Wrong passwords per canonical path
+    pub load_retry_at: Option<Instant>, // Backoff after a wrong password
 }
 
 impl Editor {
@@ -43,18 +198,77 @@ impl Editor {
     ) -> Self {
         let now = Instant::now();
         Self {
-            storage: MemoryBuffer::new(1024 * 64, encryption_key), // 64KB pinned storage
+            storage: MemoryBuffer::new_lenient(1024 * 64, encryption_key), // 64KB pinned storage
             cursor_position: 0,
+            selection_anchor: None,
             scroll_offset: 0,
+            h_scroll_offset: 0,
+            saved_generation: None,
+            tabs: Vec::new(),
+            active_tab: 0,
+            note_id: 0,
+            next_note_id: 1,
+            close_armed_at: None,
             last_input: now,
             idle_timeout: idle_timeout_secs.map(Duration::from_secs_f64),
             ttl_expiry: ttl_minutes.map(|m| now + Duration::from_secs_f64(m * 60.0)),
+            ttl_start: now,
+            ttl_extension: ttl_minutes.map(|m| Duration::from_secs_f64(m * 60.0)),
+            max_ttl_extensions: None,
+            ttl_extensions: 0,
+            key_rotation: None,
+            unlock_hash: None,
+            swap_active: false,
+            last_key_rotation: now,
+            theme: Theme::default(),
+            highlight_current_line: false,
+            ruler: None,
+            key_bindings: KeyBindings::default(),
+            status_format: DEFAULT_STATUS_FORMAT.to_string(),
+            reading_wpm: 200,
             show_markdown: false,
+            split_preview: false,
             read_only,
+            tab_width: 4,
+            wrap: false,
+            show_whitespace: false,
+            line_numbers: LineNumbers::Off,
+            redacted: false,
+            spell_check: false,
+            #[cfg(feature = "spellcheck")]
+            dictionary: None,
+            #[cfg(feature = "spellcheck")]
+            spell_cache: None,
+            view_cache: LockedString::new(),
+            display_cache: LockedString::new(),
+            view_generation: None,
+            display_key: None,
+            word_count: None,
+            line_index: Vec::new(),
+            line_index_generation: None,
             input_mode: InputMode::Normal,
             path_buffer: String::new(),
-            password_buffer: String::new(),
+            path_candidates: None,
+            goto_buffer: String::new(),
+            password_buffer: SecretBuffer::new(),
+            password_strength: 0,
+            confirm_buffer: SecretBuffer::new(),
+            show_password: false,
+            read_only_output: true,
+            scrub_on_overwrite: false,
+            crlf_on_save: false,
+            keyfile: None,
+            recipients: Vec::new(),
+            identity: None,
+            autosave: None,
+            min_password_score: None,
             status_message: None,
+            status_timeout: Duration::from_secs(3),
+            max_buffer_bytes: None,
+            clipboard_armed_at: None,
+            max_load_attempts: None,
+            failed_loads: HashMap::new(),
+            load_retry_at: None,
         }
     }
 
@@ -64,54 +278,142 @@ impl Editor {
                 if self.read_only {
                     return;
                 }
-                let mut content = self.storage.to_string();
-                let byte_idx = content
-                    .char_indices()
-                    .map(|(i, _)| i)
-                    .nth(self.cursor_position)
-                    .unwrap_or(content.len());
-                content.insert(byte_idx, ch);
-                self.storage.update(&content);
-                content.zeroize();
-                self.cursor_position += 1;
+                if !self.has_room(ch.len_utf8()) {
+                    return;
+                }
+                self.delete_selection();
+                let mut encoded = [0u8; 4];
+                self.storage
+                    .insert_str_at(self.cursor_position, ch.encode_utf8(&mut encoded));
+                encoded.zeroize();
+                self.cursor_position += ch.len_utf8();
             }
-            InputMode::EnterPath => {
+            InputMode::EnterPath | InputMode::OpenPath | InputMode::InsertFile => {
                 self.path_buffer.push(ch);
             }
-            InputMode::EnterPassword => {
+            InputMode::EnterPassword | InputMode::OpenPassword | InputMode::Locked => {
                 self.password_buffer.push(ch);
+                self.update_password_strength();
+            }
+            InputMode::ConfirmPassword => {
+                self.confirm_buffer.push(ch);
             }
+            InputMode::GotoLine => {
+                self.goto_buffer.push(ch);
+            }
+            InputMode::ConfirmOverwrite => match ch {
+                'y' | 'Y' => self.proceed_to_save(),
+                'n' | 'N' => self.input_mode = InputMode::EnterPath,
+                _ => {}
+            },
+            InputMode::ConfirmClear => match ch {
+                'y' | 'Y' => self.clear_note(),
+                'n' | 'N' => self.input_mode = InputMode::Normal,
+                _ => {}
+            },
+            InputMode::Help => {}
         }
         self.last_input = Instant::now();
     }
 
+    /// Inserts a whole string at the cursor in one buffer update. In popups
+    /// the text goes to the active input field instead, minus line breaks
+    /// and other control characters.
+    pub fn insert_str(&mut self, s: &str) {
+        if self.input_mode != InputMode::Normal {
+            for ch in s.chars().filter(|c| !c.is_control()) {
+                self.handle_input(ch);
+            }
+            return;
+        }
+        let mut text = normalize_newlines(s);
+        if !self.read_only && self.has_room(text.len()) {
+            self.delete_selection();
+            self.storage.insert_str_at(self.cursor_position, &text);
+            self.cursor_position += text.len();
+            self.last_input = Instant::now();
+        }
+        text.zeroize();
+    }
+
+    /// Scores a new password once per edit rather than on every frame.
+    fn update_password_strength(&mut self) {
+        if self.input_mode == InputMode::EnterPassword {
+            self.password_strength = password_score(self.password_buffer.as_str());
+        }
+    }
+
+    /// Whether `extra` more bytes fit under `max_buffer_bytes` once the
+    /// selection they replace is gone. Says so in the status bar when they
+    /// don't, so the buffer never grows past it. Check this before deleting
+    /// the selection, so a refused insert loses nothing.
+    fn has_room(&mut self, extra: usize) -> bool {
+        match self.max_buffer_bytes {
+            Some(max) if self.room_left().is_some_and(|room| extra > room) => {
+                self.set_status(&format!("Buffer full (max {})", format_size(max as u64)));
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Bytes that can still be added under `max_buffer_bytes`, counting the
+    /// selection as free. None if there is no limit.
+    fn room_left(&self) -> Option<usize> {
+        let selected = self.selection().map_or(0, |range| range.len());
+        let used = self.storage.used_bytes() - selected;
+        self.max_buffer_bytes.map(|max| max.saturating_sub(used))
+    }
+
+    /// Deletes back to the start of the previous word in one edit. Stops at
+    /// the start of the line; from there it deletes the line break instead.
+    pub fn delete_word_backward(&mut self) {
+        if self.input_mode != InputMode::Normal || self.read_only || self.delete_selection() {
+            return;
+        }
+        if self.cursor_position == 0 {
+            return;
+        }
+        let cursor = self.cursor_position;
+        let start = self.with_content(|c| prev_word_start(c, cursor));
+        self.storage.remove_range(start..cursor);
+        self.cursor_position = start;
+    }
+
     pub fn delete_backspace(&mut self) {
         match self.input_mode {
             InputMode::Normal => {
-                if self.read_only {
+                if self.read_only || self.delete_selection() {
                     return;
                 }
                 if self.cursor_position > 0 {
-                    let mut content = self.storage.to_string();
-                    self.cursor_position -= 1;
-                    if let Some((byte_idx, _)) = content.char_indices().nth(self.cursor_position) {
-                        content.remove(byte_idx);
-                        self.storage.update(&content);
-                    }
-                    content.zeroize();
+                    // Delete the whole grapheme, e.g. an emoji ZWJ sequence.
+                    let cursor = self.cursor_position;
+                    let start = self.with_content(|c| prev_grapheme(c, cursor));
+                    self.storage.remove_range(start..cursor);
+                    self.cursor_position = start;
                 }
             }
-            InputMode::EnterPath => {
+            InputMode::EnterPath | InputMode::OpenPath | InputMode::InsertFile => {
                 self.path_buffer.pop();
             }
-            InputMode::EnterPassword => {
+            InputMode::EnterPassword | InputMode::OpenPassword | InputMode::Locked => {
                 self.password_buffer.pop();
+                self.update_password_strength();
+            }
+            InputMode::ConfirmPassword => {
+                self.confirm_buffer.pop();
             }
+            InputMode::GotoLine => {
+                self.goto_buffer.pop();
+            }
+            InputMode::ConfirmOverwrite | InputMode::ConfirmClear | InputMode::Help => {}
         }
         self.last_input = Instant::now();
     }
 
     pub fn handle_newline(&mut self) {
+        self.last_input = Instant::now();
         match self.input_mode {
             InputMode::Normal => {
                 if !self.read_only {
@@ -120,44 +422,275 @@ impl Editor {
             }
             InputMode::EnterPath => {
                 if !self.path_buffer.trim().is_empty() {
-                    self.input_mode = InputMode::EnterPassword;
+                    // Catch a bad directory before a password is typed for nothing.
+                    if let Err(e) = check_save_dir(Path::new(&self.save_path())) {
+                        self.set_status(&e);
+                        return;
+                    }
+                    if Path::new(&self.save_path()).exists() {
+                        self.input_mode = InputMode::ConfirmOverwrite;
+                    } else {
+                        self.proceed_to_save();
+                    }
                 }
             }
+            InputMode::ConfirmOverwrite | InputMode::ConfirmClear => {}
             InputMode::EnterPassword => {
                 if !self.password_buffer.is_empty() {
-                    if self.password_buffer.len() < 8 {
-                        self.set_status("PASSWORD TOO SHORT (MIN 8 CHARS)");
+                    if self.password_buffer.len() < MIN_PASSWORD_LEN {
+                        self.set_status(&format!(
+                            "PASSWORD TOO SHORT (MIN {} CHARS)",
+                            MIN_PASSWORD_LEN
+                        ));
                         return;
                     }
-                    // Perform Save
-                    let content = self.storage.to_string();
-                    let mut final_path = self.path_buffer.trim().to_string();
-                    if !final_path.ends_with(".amnesio") && !final_path.contains('.') {
-                        final_path.push_str(".amnesio");
+                    if let Some(min) = self.min_password_score {
+                        if self.password_strength < min {
+                            self.set_status(&format!(
+                                "PASSWORD TOO WEAK ({}/4, NEED {}/4)",
+                                self.password_strength, min
+                            ));
+                            return;
+                        }
+                    }
+                    self.input_mode = InputMode::ConfirmPassword;
+                }
+            }
+            InputMode::ConfirmPassword => {
+                if !self.confirm_buffer.is_empty() {
+                    if self.confirm_buffer.as_str() == self.password_buffer.as_str() {
+                        self.perform_save();
+                    } else {
+                        self.set_status("Passwords don't match");
+                        self.input_mode = InputMode::EnterPassword;
+                    }
+                    self.password_buffer.clear();
+                    self.confirm_buffer.clear();
+                }
+            }
+            InputMode::OpenPath => {
+                if !self.path_buffer.trim().is_empty() {
+                    // Only password files prompt for a password.
+                    match persistence::key_source(self.path_buffer.trim()) {
+                        Ok(persistence::KeySource::Password) | Err(_) => {
+                            self.input_mode = InputMode::OpenPassword
+                        }
+                        Ok(_) => self.perform_open(),
                     }
+                }
+            }
+            InputMode::InsertFile => {
+                if !self.path_buffer.trim().is_empty() {
+                    self.perform_insert_file();
+                }
+            }
+            InputMode::Locked => {
+                if !self.password_buffer.is_empty() {
+                    self.unlock();
+                }
+            }
+            InputMode::GotoLine => {
+                if !self.goto_buffer.trim().is_empty() {
+                    self.perform_goto();
+                }
+            }
+            InputMode::OpenPassword => {
+                if let Some(wait) = self.load_retry_remaining() {
+                    self.set_status(&format!("Wait {}s before retrying", wait.as_secs() + 1));
+                    return;
+                }
+                if !self.password_buffer.is_empty() {
+                    self.perform_open();
+                }
+            }
+            InputMode::Help => {}
+        }
+    }
+
+    /// Wrong passwords entered for `path` so far. Every file keeps its own
+    /// count, so trying other files in between doesn't reset it.
+    pub fn failed_load_attempts(&self, path: &str) -> u32 {
+        self.failed_loads
+            .get(&attempts_key(path))
+            .copied()
+            .unwrap_or(0)
+    }
 
-                    let result =
-                        persistence::save_encrypted(&final_path, &content, &self.password_buffer);
+    fn perform_open(&mut self) {
+        let path = self.path_buffer.trim().to_string();
+        let key = attempts_key(&path);
 
-                    match result {
-                        Ok(_) => {
-                            self.set_status(&format!("Saved as: {}", final_path));
-                        }
-                        Err(e) => {
-                            self.set_status(&format!("Error: {}", e));
+        let result = if self.password_buffer.is_empty() {
+            match (persistence::key_source(&path), &self.identity) {
+                (Ok(persistence::KeySource::Recipients), Some(identity)) => {
+                    persistence::load_with_identity_lossy(&path, identity)
+                }
+                (Ok(persistence::KeySource::Recipients), None) => Err(
+                    PersistenceError::WrongKeySource(persistence::KeySource::Recipients),
+                ),
+                _ => self.with_keyfile(|credential| {
+                    persistence::load_with_credential_lossy(&path, credential)
+                }),
+            }
+        } else {
+            persistence::load_with_credential_lossy(
+                &path,
+                persistence::Credential::Password(self.password_buffer.as_str()),
+            )
+        };
+        self.password_buffer.clear();
+
+        match result {
+            Ok((mut content, _, damaged)) => {
+                let mut normalized = normalize_newlines(&content);
+                content.zeroize();
+                self.storage.update(&normalized);
+                normalized.zeroize();
+                self.cursor_position = 0;
+                self.scroll_offset = 0;
+                self.h_scroll_offset = 0;
+                self.failed_loads.remove(&key);
+                self.load_retry_at = None;
+                self.input_mode = InputMode::Normal;
+                self.mark_saved();
+                if damaged {
+                    self.set_status(&format!("{}: {}", path, DAMAGED_TEXT_WARNING));
+                } else {
+                    self.set_status(&format!("Opened: {}", path));
+                }
+            }
+            Err(PersistenceError::DecryptionFailed) => {
+                let attempts = self.failed_loads.entry(key.clone()).or_insert(0);
+                *attempts += 1;
+                let attempts = *attempts;
+                // 1s, 2s, 4s, ... capped at 64s
+                let backoff = 1u64 << (attempts - 1).min(6);
+                self.load_retry_at = Some(Instant::now() + Duration::from_secs(backoff));
+                match self.max_load_attempts {
+                    Some(max) if attempts >= max => {
+                        self.failed_loads.remove(&key);
+                        self.load_retry_at = None;
+                        self.input_mode = InputMode::Normal;
+                        match persistence::shred_file(&path) {
+                            Ok(()) => self.set_status("Too many failed attempts. File destroyed."),
+                            Err(e) => self.set_status(&format!("Error destroying file: {}", e)),
                         }
                     }
-
-                    // Cleanup
-                    self.password_buffer.zeroize();
-                    self.password_buffer.clear();
-                    self.input_mode = InputMode::Normal;
+                    Some(max) => {
+                        self.set_status(&format!("Wrong password ({}/{} attempts)", attempts, max));
+                    }
+                    None => self.set_status("Wrong password"),
                 }
             }
+            Err(e) => {
+                self.input_mode = InputMode::Normal;
+                self.set_status(&format!("Error: {}", e));
+            }
+        }
+    }
+
+    /// Moves on from an accepted save path: straight to saving with a
+    /// keyfile or age recipients, otherwise to the password prompt.
+    fn proceed_to_save(&mut self) {
+        if self.keyfile.is_some() || !self.recipients.is_empty() {
+            self.perform_save();
+        } else {
+            self.input_mode = InputMode::EnterPassword;
+        }
+    }
+
+    /// The path typed into the save popup, with `.amnesio` added if it has
+    /// no extension.
+    fn save_path(&self) -> String {
+        let mut path = self.path_buffer.trim().to_string();
+        if !path.ends_with(".amnesio") && !path.contains('.') {
+            path.push_str(".amnesio");
+        }
+        path
+    }
+
+    fn perform_save(&mut self) {
+        let mut content = self.content_for_save();
+        let final_path = self.save_path();
+
+        let metadata = persistence::FileMetadata::for_content(&content);
+        let save = |credential: persistence::Credential| {
+            persistence::save_with_credential(
+                &final_path,
+                &content,
+                credential,
+                self.read_only_output,
+                self.scrub_on_overwrite,
+                &metadata,
+            )
+        };
+        let result = if !self.recipients.is_empty() {
+            persistence::save_to_recipients_with_metadata(
+                &final_path,
+                &content,
+                &self.recipients,
+                self.read_only_output,
+                self.scrub_on_overwrite,
+                &metadata,
+            )
+        } else if self.keyfile.is_some() {
+            self.with_keyfile(save)
+        } else {
+            save(persistence::Credential::Password(
+                self.password_buffer.as_str(),
+            ))
+        };
+        content.zeroize();
+
+        match result {
+            Ok(info) => {
+                self.mark_saved();
+                self.set_status(&format!(
+                    "Saved {} to {}",
+                    format_size(info.bytes_written),
+                    info.path.display()
+                ));
+            }
+            Err(e) => {
+                self.set_status(&format!("Error: {}", e));
+            }
         }
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// The note as it should be written to disk, with CRLF line endings
+    /// if `crlf_on_save` is set.
+    fn content_for_save(&self) -> String {
+        let mut content = self.storage.to_string();
+        if !self.crlf_on_save {
+            return content;
+        }
+        let crlf = to_crlf(&content);
+        content.zeroize();
+        crlf
+    }
+
+    /// Reads the configured keyfile, runs `f` with it and wipes its bytes.
+    fn with_keyfile<T>(
+        &self,
+        f: impl FnOnce(persistence::Credential) -> Result<T, PersistenceError>,
+    ) -> Result<T, PersistenceError> {
+        let path = self
+            .keyfile
+            .as_ref()
+            .ok_or(PersistenceError::WrongKeySource(
+                persistence::KeySource::Keyfile,
+            ))?;
+        let mut bytes = persistence::read_keyfile(path)?;
+        let result = f(persistence::Credential::Keyfile(&bytes));
+        bytes.zeroize();
+        result
     }
 
     pub fn enter_save_mode(&mut self) {
+        if self.input_mode == InputMode::Locked {
+            return;
+        }
         if self.read_only {
             self.set_status("Cannot save in Read-Only mode.");
             return;
@@ -165,27 +698,250 @@ impl Editor {
         self.input_mode = InputMode::EnterPath;
         self.path_buffer.clear();
         self.password_buffer.clear();
+        self.confirm_buffer.clear();
+        self.show_password = false;
+    }
+
+    /// Replaces the file name in the save popup with a random one, keeping
+    /// any directory already typed. The user still confirms it with Enter.
+    pub fn generate_filename(&mut self) {
+        if self.input_mode != InputMode::EnterPath {
+            return;
+        }
+        let dir_len = self
+            .path_buffer
+            .rfind(std::path::is_separator)
+            .map_or(0, |i| i + 1);
+        self.path_buffer.truncate(dir_len);
+        self.path_buffer.push_str(&persistence::random_filename());
+    }
+
+    /// Tab in the save/open popup: completes the path against the
+    /// filesystem. A single match is filled in, several are completed to
+    /// their common prefix and listed under the path.
+    pub fn complete_path(&mut self) {
+        if !matches!(
+            self.input_mode,
+            InputMode::EnterPath | InputMode::OpenPath | InputMode::InsertFile
+        ) {
+            return;
+        }
+        let dir_len = self
+            .path_buffer
+            .rfind(std::path::is_separator)
+            .map_or(0, |i| i + 1);
+        let candidates = path_completions(&self.path_buffer);
+        let completed = match candidates.as_slice() {
+            [] => {
+                self.set_status("No matching files");
+                return;
+            }
+            [only] => only.clone(),
+            [first, rest @ ..] => rest.iter().fold(first.clone(), |prefix, c| {
+                let len = prefix
+                    .char_indices()
+                    .zip(c.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map_or(0, |((i, a), _)| i + a.len_utf8());
+                prefix[..len].to_string()
+            }),
+        };
+        self.path_buffer.truncate(dir_len);
+        self.path_buffer.push_str(&completed);
+        self.path_candidates =
+            (candidates.len() > 1).then(|| (self.path_buffer.clone(), candidates));
+    }
+
+    /// The candidates listed by the last ambiguous Tab, while the path is
+    /// unchanged since.
+    pub fn path_candidates(&self) -> &[String] {
+        match &self.path_candidates {
+            Some((path, candidates)) if *path == self.path_buffer => candidates,
+            _ => &[],
+        }
+    }
+
+    /// Time left before another password attempt is accepted, if any.
+    pub fn load_retry_remaining(&self) -> Option<Duration> {
+        self.load_retry_at
+            .and_then(|t| t.checked_duration_since(Instant::now()))
+            .filter(|d| !d.is_zero())
+    }
+
+    pub fn enter_open_mode(&mut self) {
+        if self.input_mode == InputMode::Locked {
+            return;
+        }
+        self.input_mode = InputMode::OpenPath;
+        self.path_buffer.clear();
+        self.password_buffer.clear();
+        self.confirm_buffer.clear();
+        self.show_password = false;
+    }
+
+    pub fn enter_insert_file_mode(&mut self) {
+        if self.input_mode != InputMode::Normal {
+            return;
+        }
+        if self.read_only {
+            self.set_status("Read-only: can't insert a file");
+            return;
+        }
+        self.input_mode = InputMode::InsertFile;
+        self.path_buffer.clear();
+    }
+
+    /// Reads the plaintext file in `path_buffer` and inserts it at the
+    /// cursor. The copy read from disk is zeroized afterwards; the file
+    /// itself is left alone, so the status says it's still there.
+    fn perform_insert_file(&mut self) {
+        let path = self.path_buffer.trim().to_string();
+        self.input_mode = InputMode::Normal;
+        self.path_buffer.clear();
+
+        // Refuse oversized files before any of them is read into memory.
+        // Devices, FIFOs and /proc files report a size of 0, so only regular
+        // files are accepted.
+        let len = match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            Ok(_) => {
+                self.set_status(&format!("Error: {} is not a regular file", path));
+                return;
+            }
+            Err(e) => {
+                self.set_status(&format!("Error: {}", e));
+                return;
+            }
+        };
+        if !self.has_room(usize::try_from(len).unwrap_or(usize::MAX)) {
+            return;
+        }
+        // The file can grow after the check, so the read is bounded as well.
+        let limit = self
+            .room_left()
+            .map_or(len, |room| room as u64)
+            .saturating_add(1);
+        let result = std::fs::File::open(&path)
+            .and_then(|file| crate::secret::read_all_zeroizing(file.take(limit)));
+        let mut text = match result {
+            Ok(bytes) => crate::secret::into_string_zeroizing(bytes),
+            Err(e) => {
+                self.set_status(&format!("Error: {}", e));
+                return;
+            }
+        };
+        if !self.has_room(text.len()) {
+            text.zeroize();
+            return;
+        }
+        self.insert_str(&text);
+        let len = text.len();
+        text.zeroize();
+        self.set_status(&format!(
+            "Inserted {} from {}. The original is still on disk, unencrypted.",
+            format_size(len as u64),
+            path
+        ));
+    }
+
+    pub fn enter_goto_mode(&mut self) {
+        if self.input_mode != InputMode::Normal {
+            return;
+        }
+        self.input_mode = InputMode::GotoLine;
+        self.goto_buffer.clear();
+    }
+
+    /// Moves the cursor to the start of the (1-based) line in `goto_buffer`,
+    /// clamped to the first and last lines.
+    fn perform_goto(&mut self) {
+        let line = match self.goto_buffer.trim().parse::<usize>() {
+            Ok(line) => line,
+            Err(_) => {
+                let msg = format!("Not a line number: {}", self.goto_buffer.trim());
+                self.set_status(&msg);
+                self.goto_buffer.clear();
+                return;
+            }
+        };
+        self.goto_buffer.clear();
+        self.input_mode = InputMode::Normal;
+
+        self.refresh_line_index();
+        self.cursor_position = self.line_index[line.clamp(1, self.line_index.len()) - 1];
     }
 
     pub fn exit_popup(&mut self) {
+        if self.input_mode == InputMode::Locked {
+            self.password_buffer.clear();
+            return;
+        }
         self.input_mode = InputMode::Normal;
-        self.password_buffer.zeroize();
+        self.last_input = Instant::now();
+        self.goto_buffer.clear();
         self.password_buffer.clear();
+        self.confirm_buffer.clear();
         self.path_buffer.clear();
+        self.show_password = false;
+    }
+
+    /// Opens the keybinding help from the editor, or closes it.
+    pub fn toggle_help(&mut self) {
+        match self.input_mode {
+            InputMode::Normal => self.input_mode = InputMode::Help,
+            InputMode::Help => self.input_mode = InputMode::Normal,
+            _ => {}
+        }
+    }
+
+    /// Shows or masks the password while one is being entered.
+    pub fn toggle_password_visibility(&mut self) {
+        if matches!(
+            self.input_mode,
+            InputMode::EnterPassword
+                | InputMode::ConfirmPassword
+                | InputMode::OpenPassword
+                | InputMode::Locked
+        ) {
+            self.show_password = !self.show_password;
+        }
+    }
+
+    /// The password as shown in the popup: masked unless toggled visible.
+    /// The clear text is borrowed, never copied.
+    fn password_line<'a>(&self, secret: &'a SecretBuffer) -> Line<'a> {
+        if self.show_password {
+            Line::raw(secret.as_str())
+        } else {
+            Line::raw("*".repeat(secret.len()))
+        }
     }
 
+    /// Moves the cursor by `offset` grapheme clusters. With a selection,
+    /// it collapses to the selection's start or end instead.
     pub fn move_cursor(&mut self, offset: isize) {
         if self.input_mode != InputMode::Normal {
             return;
         }
+        if let Some(range) = self.selection().filter(|_| offset != 0) {
+            self.selection_anchor = None;
+            self.cursor_position = if offset < 0 { range.start } else { range.end };
+            self.last_input = Instant::now();
+            return;
+        }
 
-        let mut content = self.storage.to_string();
-        let char_count = content.chars().count();
-        let new_pos = (self.cursor_position as isize + offset)
-            .max(0)
-            .min(char_count as isize);
-        self.cursor_position = new_pos as usize;
-        content.zeroize();
+        let mut pos = self.cursor_position;
+        self.with_content(|content| {
+            for _ in 0..offset.unsigned_abs() {
+                pos = if offset < 0 {
+                    prev_grapheme(content, pos)
+                } else {
+                    next_grapheme(content, pos)
+                };
+            }
+        });
+        self.cursor_position = pos;
         self.last_input = Instant::now();
     }
 
@@ -193,44 +949,96 @@ impl Editor {
         if self.input_mode != InputMode::Normal {
             return;
         }
+        self.selection_anchor = None;
 
-        let mut content = self.storage.to_string();
-        let chars: Vec<char> = content.chars().collect();
-        let mut cur_line = 0;
-        let mut cur_col = 0;
-        let mut lines: Vec<Vec<char>> = vec![vec![]];
-
-        for (i, c) in chars.iter().enumerate() {
-            if i == self.cursor_position {
-                cur_line = lines.len() - 1;
-                cur_col = lines.last().unwrap().len();
-            }
-            if *c == '\n' {
-                lines.push(vec![]);
-            } else {
-                lines.last_mut().unwrap().push(*c);
-            }
+        self.refresh_line_index();
+        let content = self.view_cache.as_str();
+        let lines = &self.line_index;
+        let cur_line = line_of(lines, self.cursor_position);
+        let cur_col = line_text(content, lines, cur_line)
+            .grapheme_indices(true)
+            .take_while(|&(i, _)| lines[cur_line] + i < self.cursor_position)
+            .count();
+
+        let target_line = cur_line
+            .saturating_add_signed(direction)
+            .min(lines.len() - 1);
+        let offset: usize = line_text(content, lines, target_line)
+            .graphemes(true)
+            .take(cur_col)
+            .map(str::len)
+            .sum();
+        self.cursor_position = lines[target_line] + offset;
+        self.last_input = Instant::now();
+    }
+
+    /// Moves the cursor to the start of the buffer.
+    pub fn move_to_start(&mut self) {
+        if self.input_mode != InputMode::Normal {
+            return;
         }
+        self.selection_anchor = None;
+        self.cursor_position = 0;
+        self.last_input = Instant::now();
+    }
 
-        if self.cursor_position == chars.len() {
-            cur_line = lines.len() - 1;
-            cur_col = lines.last().unwrap().len();
+    /// Moves the cursor to the end of the buffer.
+    pub fn move_to_end(&mut self) {
+        if self.input_mode != InputMode::Normal {
+            return;
         }
+        self.selection_anchor = None;
+        self.cursor_position = self.storage.byte_len();
+        self.last_input = Instant::now();
+    }
 
-        let target_line = (cur_line as isize + direction)
-            .max(0)
-            .min(lines.len() as isize - 1) as usize;
-        let target_col = cur_col.min(lines[target_line].len());
+    /// Selects the whole note, leaving the cursor at the end. Typing,
+    /// pasting or deleting then replaces it; an arrow key collapses it.
+    pub fn select_all(&mut self) {
+        if self.input_mode != InputMode::Normal || self.storage.byte_len() == 0 {
+            return;
+        }
+        self.selection_anchor = Some((0, self.storage.generation()));
+        self.cursor_position = self.storage.byte_len();
+        self.last_input = Instant::now();
+    }
 
-        let mut new_idx = 0;
-        for i in 0..target_line {
-            new_idx += lines[i].len() + 1;
+    /// The selected byte range, if any. Any change to the text the editor
+    /// didn't make through the selection (opening, clearing, ...) drops it.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let (anchor, generation) = self.selection_anchor?;
+        if generation != self.storage.generation() || anchor == self.cursor_position {
+            return None;
         }
-        new_idx += target_col;
+        Some(anchor.min(self.cursor_position)..anchor.max(self.cursor_position))
+    }
 
-        self.cursor_position = new_idx;
-        content.zeroize();
+    /// Deletes the selection in one edit. Returns false if there was none.
+    fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            return false;
+        };
+        self.selection_anchor = None;
+        self.storage.remove_range(range.clone());
+        self.cursor_position = range.start;
         self.last_input = Instant::now();
+        true
+    }
+
+    /// Rebuilds `line_index` if the text changed since it was last built.
+    fn refresh_line_index(&mut self) {
+        self.refresh_view_cache();
+        let generation = self.storage.generation();
+        if self.line_index_generation != Some(generation) {
+            self.line_index = line_starts(self.view_cache.as_str());
+            self.line_index_generation = Some(generation);
+        }
+    }
+
+    /// Runs `f` over the current plaintext, reusing the cached decryption.
+    fn with_content<R>(&mut self, f: impl FnOnce(&str) -> R) -> R {
+        self.refresh_view_cache();
+        f(self.view_cache.as_str())
     }
 
     pub fn toggle_markdown(&mut self) {
@@ -238,168 +1046,898 @@ impl Editor {
         self.last_input = Instant::now();
     }
 
-    pub fn set_status(&mut self, msg: &str) {
-        self.status_message = Some((msg.to_string(), Instant::now()));
+    pub fn toggle_split_preview(&mut self) {
+        self.split_preview = !self.split_preview;
+        self.h_scroll_offset = 0;
+        self.last_input = Instant::now();
     }
 
-    pub fn is_timed_out(&self) -> bool {
-        let now = Instant::now();
-        if let Some(timeout) = self.idle_timeout {
-            if now.duration_since(self.last_input) >= timeout {
-                return true;
-            }
-        }
-        if let Some(expiry) = self.ttl_expiry {
-            if now >= expiry {
-                return true;
-            }
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.h_scroll_offset = 0;
+        self.last_input = Instant::now();
+    }
+
+    #[cfg(feature = "spellcheck")]
+    pub fn toggle_spell_check(&mut self) {
+        if self.dictionary.is_none() {
+            self.set_status("No dictionary loaded; set `dictionary` in the config");
+            return;
         }
-        false
+        self.spell_check = !self.spell_check;
+        self.last_input = Instant::now();
     }
 
-    pub fn draw(&mut self, frame: &mut Frame) {
-        let mut content = self.storage.to_string();
-        let chunks = Layout::default()
+    #[cfg(not(feature = "spellcheck"))]
+    pub fn toggle_spell_check(&mut self) {
+        self.set_status("Spell check isn't available in this build");
+    }
+
+    /// Cycles the gutter through off, absolute and relative numbers.
+    pub fn cycle_line_numbers(&mut self) {
+        self.line_numbers = self.line_numbers.next();
+        self.last_input = Instant::now();
+    }
+
+    /// Masks or reveals the note on screen. Editing and navigation keep
+    /// working while it's masked; only drawing changes.
+    pub fn toggle_redaction(&mut self) {
+        self.redacted = !self.redacted;
+        self.last_input = Instant::now();
+    }
+
+    pub fn toggle_whitespace(&mut self) {
+        self.show_whitespace = !self.show_whitespace;
+        self.last_input = Instant::now();
+    }
+
+    /// Zeroes every tab's note, the popup inputs and any status text right
+    /// away, leaving a single empty tab.
+    pub fn wipe(&mut self) {
+        self.storage.wipe();
+        for tab in &mut self.tabs {
+            tab.storage.wipe();
+        }
+        self.tabs.clear();
+        self.active_tab = 0;
+        self.view_cache.set("");
+        self.display_cache.set("");
+        self.password_buffer.clear();
+        self.confirm_buffer.clear();
+        self.path_buffer.zeroize();
+        self.status_message = None;
+        self.cursor_position = 0;
+        self.scroll_offset = 0;
+        self.h_scroll_offset = 0;
+    }
+
+    /// Records the current note as saved, e.g. after loading it from a file.
+    pub fn mark_saved(&mut self) {
+        self.saved_generation = Some(self.storage.generation());
+    }
+
+    /// Returns true if the note has text that was changed since it was last
+    /// saved or opened.
+    pub fn is_dirty(&self) -> bool {
+        self.storage.char_len() > 0 && self.saved_generation != Some(self.storage.generation())
+    }
+
+    /// Starts a fresh note in the current tab: the note is zeroized and
+    /// emptied, asking first if it has unsaved changes.
+    pub fn request_clear(&mut self) {
+        if self.input_mode != InputMode::Normal {
+            return;
+        }
+        if self.read_only {
+            self.set_status("Cannot clear in Read-Only mode.");
+            return;
+        }
+        if self.is_dirty() {
+            self.input_mode = InputMode::ConfirmClear;
+        } else {
+            self.clear_note();
+        }
+    }
+
+    fn clear_note(&mut self) {
+        self.storage.wipe();
+        self.cursor_position = 0;
+        self.scroll_offset = 0;
+        self.h_scroll_offset = 0;
+        self.saved_generation = None;
+        self.input_mode = InputMode::Normal;
+        self.last_input = Instant::now();
+        self.set_status("Note cleared");
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.tabs.len() + 1
+    }
+
+    /// Index of the tab being shown, from 0.
+    pub fn active_tab(&self) -> usize {
+        self.active_tab
+    }
+
+    /// Opens an empty note in a new tab after the current one and shows it.
+    /// New notes are encrypted in RAM if the current one is, under their own key.
+    pub fn new_tab(&mut self) {
+        if self.input_mode != InputMode::Normal {
+            return;
+        }
+        if self.tab_count() >= MAX_TABS {
+            self.set_status(&format!("At most {} tabs", MAX_TABS));
+            return;
+        }
+        let mut key = self.storage.is_encrypted().then(stealth::derive_key);
+        let storage = MemoryBuffer::new(1024 * 64, key);
+        key.zeroize();
+        let storage = match storage {
+            Ok(storage) => storage,
+            Err(e) => {
+                self.set_status(&format!("Can't open a tab: memory can't be locked ({})", e));
+                return;
+            }
+        };
+        let mut tab = Tab {
+            id: self.next_note_id,
+            storage,
+            cursor_position: 0,
+            scroll_offset: 0,
+            h_scroll_offset: 0,
+            saved_generation: None,
+        };
+        self.next_note_id += 1;
+        self.swap_tab(&mut tab);
+        self.tabs.insert(self.active_tab, tab);
+        self.active_tab += 1;
+    }
+
+    /// Shows tab `index` (from 0). Does nothing if there is no such tab.
+    pub fn switch_tab(&mut self, index: usize) {
+        if self.input_mode != InputMode::Normal
+            || index == self.active_tab
+            || index > self.tabs.len()
+        {
+            return;
+        }
+        let mut tab = self.tabs.remove(if index < self.active_tab {
+            index
+        } else {
+            index - 1
+        });
+        self.swap_tab(&mut tab);
+        let previous = if self.active_tab < index {
+            self.active_tab
+        } else {
+            self.active_tab - 1
+        };
+        self.tabs.insert(previous, tab);
+        self.active_tab = index;
+    }
+
+    /// Shows the next tab, wrapping around after the last.
+    pub fn next_tab(&mut self) {
+        self.switch_tab((self.active_tab + 1) % self.tab_count());
+    }
+
+    /// Wipes the current tab's note and closes the tab. A note that isn't
+    /// empty takes a second press within 3 seconds.
+    pub fn close_tab(&mut self) {
+        if self.input_mode != InputMode::Normal {
+            return;
+        }
+        if self.tabs.is_empty() {
+            self.set_status("This is the only tab");
+            return;
+        }
+        let armed = self
+            .close_armed_at
+            .take()
+            .is_some_and(|t| t.elapsed() < Duration::from_secs(3));
+        if self.storage.char_len() > 0 && !armed {
+            self.close_armed_at = Some(Instant::now());
+            self.set_status(&format!(
+                "Press {} again to close this tab and wipe its note",
+                self.key_bindings.key(Action::CloseTab)
+            ));
+            return;
+        }
+        self.storage.wipe();
+        if self
+            .autosave
+            .as_ref()
+            .is_some_and(|a| a.note == self.note_id)
+        {
+            self.autosave = None;
+            self.set_status("Auto-save is off: its note was closed");
+        }
+        let index = self.active_tab.min(self.tabs.len() - 1);
+        let mut tab = self.tabs.remove(index);
+        self.swap_tab(&mut tab);
+        self.active_tab = index;
+        // `tab` now holds the closed, wiped note; dropping it zeroes it again.
+    }
+
+    /// Swaps the shown note with `tab` and drops caches of the old one.
+    fn swap_tab(&mut self, tab: &mut Tab) {
+        std::mem::swap(&mut self.note_id, &mut tab.id);
+        std::mem::swap(&mut self.storage, &mut tab.storage);
+        std::mem::swap(&mut self.cursor_position, &mut tab.cursor_position);
+        std::mem::swap(&mut self.scroll_offset, &mut tab.scroll_offset);
+        std::mem::swap(&mut self.h_scroll_offset, &mut tab.h_scroll_offset);
+        std::mem::swap(&mut self.saved_generation, &mut tab.saved_generation);
+        self.selection_anchor = None;
+        // Generations are per buffer, so the caches can't tell notes apart.
+        self.view_generation = None;
+        self.display_key = None;
+        self.word_count = None;
+        self.line_index_generation = None;
+        #[cfg(feature = "spellcheck")]
+        {
+            self.spell_cache = None;
+        }
+        self.close_armed_at = None;
+        self.last_input = Instant::now();
+    }
+
+    pub fn set_status(&mut self, msg: &str) {
+        self.status_message = Some((msg.to_string(), Instant::now()));
+    }
+
+    /// Copying to the terminal clipboard takes two presses within 3 seconds,
+    /// since the text leaves the secure buffer. Returns the OSC 52 escape
+    /// sequence to write to the terminal once confirmed; the caller should
+    /// zeroize it after writing.
+    pub fn copy_to_clipboard(&mut self) -> Option<String> {
+        if self.input_mode != InputMode::Normal {
+            return None;
+        }
+        let armed = self
+            .clipboard_armed_at
+            .take()
+            .is_some_and(|t| t.elapsed() < Duration::from_secs(3));
+        if !armed {
+            self.clipboard_armed_at = Some(Instant::now());
+            self.set_status(&format!(
+                "Press {} again to copy ALL text to the terminal clipboard (leaves secure memory)",
+                self.key_bindings.key(Action::CopyClipboard)
+            ));
+            return None;
+        }
+        let (sequence, chars) =
+            self.with_content(|content| (osc52_sequence(content), content.chars().count()));
+        self.set_status(&format!(
+            "Copied {} chars to the terminal clipboard. It is no longer in secure memory.",
+            chars
+        ));
+        Some(sequence)
+    }
+
+    /// Pushes the TTL deadline back by `ttl_extension`.
+    pub fn extend_ttl(&mut self) {
+        let (Some(expiry), Some(extension)) = (self.ttl_expiry, self.ttl_extension) else {
+            self.set_status("No TTL to extend");
+            return;
+        };
+        if self
+            .max_ttl_extensions
+            .is_some_and(|max| self.ttl_extensions >= max)
+        {
+            self.set_status("TTL extension limit reached");
+            return;
+        }
+        let expiry = expiry + extension;
+        self.ttl_expiry = Some(expiry);
+        self.ttl_extensions += 1;
+        let left = expiry.saturating_duration_since(Instant::now()).as_secs();
+        self.set_status(&format!("TTL extended: {}m {}s left", left / 60, left % 60));
+    }
+
+    /// The idle timeout is paused while a popup is open; the TTL is not.
+    pub fn is_timed_out(&self) -> bool {
+        self.is_idle() || self.is_expired()
+    }
+
+    /// Returns true if the idle timeout has run out.
+    pub fn is_idle(&self) -> bool {
+        matches!(self.input_mode, InputMode::Normal | InputMode::Help)
+            && self
+                .idle_timeout
+                .is_some_and(|timeout| self.last_input.elapsed() >= timeout)
+    }
+
+    /// Returns true if the TTL has run out.
+    pub fn is_expired(&self) -> bool {
+        self.ttl_expiry
+            .is_some_and(|expiry| Instant::now() >= expiry)
+    }
+
+    /// Sets the password that unlocks the editor after an idle lock.
+    /// Only an Argon2 hash of it is kept.
+    pub fn set_unlock_password(&mut self, password: &str) -> Result<(), PersistenceError> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| PersistenceError::Encryption(e.to_string()))?;
+        self.unlock_hash = Some(hash.to_string());
+        Ok(())
+    }
+
+    /// Hides the content behind the unlock prompt. Any open popup is
+    /// abandoned and the plaintext caches are wiped; the buffer itself
+    /// stays encrypted in RAM.
+    pub fn lock(&mut self) {
+        if self.unlock_hash.is_none() {
+            return;
+        }
+        self.exit_popup();
+        self.view_cache.set("");
+        self.display_cache.set("");
+        self.view_generation = None;
+        self.display_key = None;
+        self.input_mode = InputMode::Locked;
+    }
+
+    fn unlock(&mut self) {
+        let unlocked = self.unlock_hash.as_deref().is_some_and(|hash| {
+            PasswordHash::new(hash).is_ok_and(|parsed| {
+                Argon2::default()
+                    .verify_password(self.password_buffer.as_str().as_bytes(), &parsed)
+                    .is_ok()
+            })
+        });
+        self.password_buffer.clear();
+        self.show_password = false;
+        if unlocked {
+            self.input_mode = InputMode::Normal;
+            self.status_message = None;
+        } else {
+            self.set_status("Wrong password");
+        }
+    }
+
+    /// Returns true if the buffer and every plaintext cache are pinned in RAM.
+    pub fn is_memory_locked(&self) -> bool {
+        self.storage.is_locked()
+            && self.tabs.iter().all(|t| t.storage.is_locked())
+            && self.view_cache.is_locked()
+            && self.display_cache.is_locked()
+    }
+
+    /// Swaps the buffer's encryption key for a fresh one once the rotation
+    /// interval has passed, limiting how long any one key stays in memory.
+    pub fn rotate_key_if_due(&mut self) {
+        let Some(interval) = self.key_rotation else {
+            return;
+        };
+        if !self.storage.is_encrypted() || self.last_key_rotation.elapsed() < interval {
+            return;
+        }
+        let mut key = stealth::derive_key();
+        self.storage.rotate_key(&key);
+        for tab in &mut self.tabs {
+            let mut tab_key = stealth::derive_key();
+            tab.storage.rotate_key(&tab_key);
+            tab_key.zeroize();
+        }
+        key.zeroize();
+        self.last_key_rotation = Instant::now();
+    }
+
+    /// Turns on auto-save of the current note to `path` every `interval`,
+    /// encrypted with `password`. It stays with that note when other tabs
+    /// are shown. Returns false, leaving auto-save off, if the password
+    /// doesn't fit in a `SecretBuffer`.
+    pub fn set_autosave(&mut self, path: PathBuf, interval: Duration, password: &str) -> bool {
+        let mut secret = SecretBuffer::new();
+        if !password.chars().all(|c| secret.push(c)) {
+            return false;
+        }
+        self.autosave = Some(Autosave {
+            path,
+            interval,
+            password: secret,
+            last: Instant::now(),
+            note: self.note_id,
+            saved_generation: None,
+        });
+        true
+    }
+
+    /// Saves the current note to the auto-save file once the interval has
+    /// passed, if it changed since the last auto-save. The previous
+    /// auto-save is replaced atomically and its bytes scrubbed.
+    pub fn autosave_if_due(&mut self) {
+        let generation = self.storage.generation();
+        match &mut self.autosave {
+            // The note can only change while it's shown, and generations
+            // are per buffer, so other tabs are never compared or saved.
+            Some(autosave) if autosave.note != self.note_id => return,
+            Some(autosave) if autosave.last.elapsed() >= autosave.interval => {
+                autosave.last = Instant::now();
+                if autosave.saved_generation == Some(generation) {
+                    return;
+                }
+            }
+            _ => return,
+        }
+
+        let mut content = self.content_for_save();
+        let Some(autosave) = &mut self.autosave else {
+            return;
+        };
+        let result = persistence::save_encrypted_with_metadata(
+            &autosave.path,
+            &content,
+            autosave.password.as_str(),
+            false,
+            true,
+            &persistence::FileMetadata::for_content(&content),
+        );
+        content.zeroize();
+        match result {
+            Ok(_) => {
+                autosave.saved_generation = Some(generation);
+                self.set_status("Auto-saved");
+            }
+            Err(e) => self.set_status(&format!("Auto-save failed: {}", e)),
+        }
+    }
+
+    fn refresh_view_cache(&mut self) {
+        let generation = self.storage.generation();
+        if self.view_generation != Some(generation) {
+            let mut content = self.storage.to_string();
+            self.view_cache.set(&content);
+            content.zeroize();
+            self.view_generation = Some(generation);
+        }
+    }
+
+    fn refresh_display_cache(&mut self, wrap_width: Option<usize>) {
+        self.refresh_view_cache();
+        let key = (self.storage.generation(), wrap_width);
+        if self.display_key != Some(key) {
+            let mut text = layout_text(self.view_cache.as_str(), self.tab_width, wrap_width);
+            self.display_cache.set(&text);
+            text.zeroize();
+            self.display_key = Some(key);
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        if self.input_mode == InputMode::Locked {
+            self.draw_locked(frame);
+            return;
+        }
+
+        let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Min(1), Constraint::Length(1)])
             .split(frame.area());
 
-        let area = chunks[0];
+        // In split mode the left pane is always the raw, editable text.
+        let (pane, preview_area) = if self.split_preview {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(chunks[0]);
+            (panes[0], Some(panes[1]))
+        } else {
+            (chunks[0], None)
+        };
+        let markdown_view = self.show_markdown && !self.split_preview;
+
+        // The gutter sits inside the border, left of the text. `area` is the
+        // pane minus the gutter, so text positions are still `area.x + 1`.
+        let gutter = if markdown_view || self.line_numbers == LineNumbers::Off {
+            0
+        } else {
+            self.refresh_view_cache();
+            let lines = self.view_cache.as_str().matches('\n').count() + 1;
+            (lines.to_string().len().max(3) + 1) as u16
+        };
+        let area = Rect {
+            x: pane.x + gutter,
+            width: pane.width.saturating_sub(gutter),
+            ..pane
+        };
         let height = area.height.saturating_sub(2) as usize;
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let wrap_width = if self.wrap { Some(width) } else { None };
 
-        // Calculate current line and column for cursor
-        let mut cur_line = 0;
-        let mut cur_col = 0;
-        let chars: Vec<char> = content.chars().collect();
-        for i in 0..self.cursor_position {
-            if i >= chars.len() {
-                break;
-            }
-            if chars[i] == '\n' {
-                cur_line += 1;
-                cur_col = 0;
-            } else {
-                cur_col += 1;
-            }
+        self.refresh_display_cache(wrap_width);
+        self.refresh_line_index();
+        // Taken out for the duration of the frame so `self` stays mutable.
+        let view = std::mem::take(&mut self.view_cache);
+        let display = std::mem::take(&mut self.display_cache);
+        let content = view.as_str();
+
+        // Calculate current (visual) line and column for cursor. Without
+        // wrapping, rows are lines, so only the cursor's line is laid out.
+        let (cur_line, cur_col) = if wrap_width.is_some() {
+            visual_position(content, self.cursor_position, self.tab_width, wrap_width)
+        } else {
+            let line = line_of(&self.line_index, self.cursor_position);
+            let start = self.line_index[line];
+            let text = line_text(content, &self.line_index, line);
+            let (_, col) =
+                visual_position(text, self.cursor_position - start, self.tab_width, None);
+            (line, col)
+        };
+
+        if cur_line < self.scroll_offset {
+            self.scroll_offset = cur_line;
+        } else if cur_line >= self.scroll_offset + height {
+            self.scroll_offset = cur_line - height + 1;
         }
 
-        if cur_line < self.scroll_offset as usize {
-            self.scroll_offset = cur_line as u16;
-        } else if cur_line >= (self.scroll_offset as usize + height) {
-            self.scroll_offset = (cur_line - height + 1) as u16;
+        // Keep the cursor column visible on long lines
+        if cur_col < self.h_scroll_offset as usize {
+            self.h_scroll_offset = cur_col as u16;
+        } else if cur_col >= (self.h_scroll_offset as usize + width) {
+            self.h_scroll_offset = u16::try_from(cur_col - width + 1).unwrap_or(u16::MAX);
         }
 
-        let title_extra = if self.show_markdown { " [MD VIEW]" } else { "" };
+        let title_extra = if markdown_view { " [MD VIEW]" } else { "" };
+        let wrap_tag = if self.wrap { " [WRAP]" } else { "" };
+        let whitespace_tag = if self.show_whitespace && !markdown_view {
+            " [WS]"
+        } else {
+            ""
+        };
         let read_only_tag = if self.read_only { " [READ-ONLY]" } else { "" };
+        let redacted_tag = if self.redacted { " [REDACTED]" } else { "" };
 
-        let editor_block = Block::default()
+        let mut editor_block = Block::default();
+        if !self.tabs.is_empty() {
+            // Tab bar: numbers only, so no other note is decrypted to draw it.
+            let spans: Vec<Span> = (0..self.tab_count())
+                .map(|i| {
+                    let label = format!(" {} ", i + 1);
+                    if i == self.active_tab {
+                        Span::styled(label, Style::default().add_modifier(Modifier::REVERSED))
+                    } else {
+                        Span::raw(label)
+                    }
+                })
+                .collect();
+            editor_block = editor_block.title_top(Line::from(spans).right_aligned());
+        }
+        let editor_block = editor_block
             .borders(Borders::ALL)
             .title(format!(
-                " amnesia - volatile-only notepad{}{}",
-                title_extra, read_only_tag
+                " amnesia - volatile-only notepad{}{}{}{}{}",
+                title_extra, wrap_tag, whitespace_tag, read_only_tag, redacted_tag
             ))
             .border_style(Style::default().fg(if self.read_only {
-                Color::Red
+                self.theme.read_only
             } else {
-                Color::DarkGray
+                self.theme.border
             }));
 
-        let widget = if self.show_markdown {
-            let lines = self.render_markdown(&content);
-            Paragraph::new(lines)
+        let text_style = if markdown_view {
+            Style::default()
+        } else {
+            Style::default()
+                .fg(self.theme.editor_fg)
+                .bg(self.theme.editor_bg)
+        };
+        // Only the visible rows are handed over: ratatui's vertical scroll
+        // is a u16 and would wrap past 65535 rows.
+        let top = self.scroll_offset;
+        let widget = if markdown_view {
+            let lines = render_markdown(display.as_str(), width);
+            Paragraph::new(visible_lines(lines, top, height))
+        } else if self.show_whitespace {
+            let lines = whitespace_lines(content, self.tab_width, wrap_width);
+            Paragraph::new(visible_lines(lines, top, height))
         } else {
-            Paragraph::new(content.clone())
-                .style(Style::default().fg(Color::White).bg(Color::Black))
+            Paragraph::new(visible_rows(display.as_str(), top, height))
         };
 
-        let widget = widget.block(editor_block).scroll((self.scroll_offset, 0));
+        let widget = widget.style(text_style).scroll((0, self.h_scroll_offset));
+
+        frame.render_widget(editor_block.style(text_style), pane);
+        let text_area = Rect::new(
+            area.x + 1,
+            area.y + 1,
+            area.width.saturating_sub(2),
+            area.height.saturating_sub(2),
+        );
+        frame.render_widget(widget, text_area);
+        if self.redacted {
+            redact(frame.buffer_mut(), text_area);
+        }
+        if gutter > 0 {
+            self.draw_gutter(frame, pane, gutter, content, wrap_width, cur_line);
+        }
+
+        #[cfg(feature = "spellcheck")]
+        if self.spell_check && !markdown_view {
+            self.underline_misspelled(frame, area, content, wrap_width);
+        }
 
-        frame.render_widget(widget, area);
+        if self.highlight_current_line {
+            let row = cur_line - self.scroll_offset;
+            if row < height {
+                let line_area = Rect::new(
+                    area.x + 1,
+                    area.y + 1 + row as u16,
+                    area.width.saturating_sub(2),
+                    1,
+                );
+                frame
+                    .buffer_mut()
+                    .set_style(line_area, Style::default().bg(self.theme.current_line));
+            }
+        }
 
-        if !self.show_markdown && self.input_mode == InputMode::Normal {
+        if let Some(range) = self.selection().filter(|_| !markdown_view) {
+            let (first, first_col) =
+                visual_position(content, range.start, self.tab_width, wrap_width);
+            let (last, last_col) = visual_position(content, range.end, self.tab_width, wrap_width);
+            let left = self.h_scroll_offset as usize;
+            let rows = first.max(top)..=last.min(top + height.saturating_sub(1));
+            for line in rows {
+                // Rows inside the selection are filled out to the edge.
+                let from = if line == first { first_col } else { 0 };
+                let to = if line == last { last_col } else { usize::MAX };
+                let from = from.saturating_sub(left).min(width);
+                let to = to.saturating_sub(left).min(width);
+                if to > from {
+                    let row_area = Rect::new(
+                        text_area.x + from as u16,
+                        text_area.y + (line - top) as u16,
+                        (to - from) as u16,
+                        1,
+                    );
+                    frame.buffer_mut().set_style(
+                        row_area.intersection(text_area),
+                        Style::default().add_modifier(Modifier::REVERSED),
+                    );
+                }
+            }
+        }
+
+        // The ruler marks the column only where no text is drawn.
+        if let Some(column) = self.ruler.filter(|_| !markdown_view) {
+            let col = column.saturating_sub(1);
+            let left = self.h_scroll_offset as usize;
+            if (left..left + width).contains(&col) && area.width > 2 {
+                let x = area.x + 1 + (col - left) as u16;
+                let buffer = frame.buffer_mut();
+                for y in area.y + 1..area.y + 1 + height as u16 {
+                    let cell = &mut buffer[(x, y)];
+                    if cell.symbol() == " " {
+                        cell.set_symbol("│").set_fg(self.theme.border);
+                    }
+                }
+            }
+        }
+
+        // The preview shares the editor's rows and scroll position.
+        if let Some(preview_area) = preview_area {
+            let preview_width = preview_area.width.saturating_sub(2) as usize;
+            let lines = render_markdown(display.as_str(), preview_width);
+            let preview = Paragraph::new(visible_lines(lines, top, height))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" Preview ")
+                        .border_style(Style::default().fg(self.theme.border)),
+                )
+                .scroll((0, self.h_scroll_offset));
+            frame.render_widget(preview, preview_area);
+            if self.redacted {
+                redact(frame.buffer_mut(), preview_area.inner(Margin::new(1, 1)));
+            }
+        }
+
+        if !markdown_view && self.input_mode == InputMode::Normal {
             frame.set_cursor_position((
-                area.x + 1 + cur_col as u16,
-                area.y + 1 + (cur_line - self.scroll_offset as usize) as u16,
+                area.x + 1 + (cur_col - self.h_scroll_offset as usize).min(width - 1) as u16,
+                area.y + 1 + (cur_line - self.scroll_offset) as u16,
             ));
         }
 
         // Status bar
-        let stealth_tag = if self.storage.is_encrypted() {
-            "[STEALTH] "
-        } else {
-            ""
-        };
+        // Unpinned plaintext on a system with swap may end up on disk.
+        let swap_risk = self.swap_active && !self.is_memory_locked();
 
-        let status_text = if let Some((msg, time)) = &self.status_message {
-            if time.elapsed().as_secs() < 3 {
-                format!(" {}", msg)
-            } else {
+        if let Some((_, time)) = &self.status_message {
+            if time.elapsed() >= self.status_timeout {
                 self.status_message = None;
-                // Revert to default status
-                format!(
-                    " {}{}:{} | Idle: {}/{}s | TTL: {}",
-                    stealth_tag,
-                    cur_line + 1,
-                    cur_col + 1,
-                    self.last_input.elapsed().as_secs(),
-                    self.idle_timeout
-                        .map(|d| d.as_secs().to_string())
-                        .unwrap_or("∞".into()),
-                    self.ttl_expiry
-                        .map(|e| if Instant::now() >= e {
-                            0
-                        } else {
-                            e.duration_since(Instant::now()).as_secs()
-                        })
-                        .map(|s| s.to_string())
-                        .unwrap_or("∞".into())
-                )
             }
-        } else {
-            format!(
-                " {}{}:{} | Idle: {}/{}s | TTL: {}",
-                stealth_tag,
-                cur_line + 1,
-                cur_col + 1,
-                self.last_input.elapsed().as_secs(),
-                self.idle_timeout
-                    .map(|d| d.as_secs().to_string())
-                    .unwrap_or("∞".into()),
-                self.ttl_expiry
-                    .map(|e| {
-                        if Instant::now() >= e {
-                            0
-                        } else {
-                            e.duration_since(Instant::now()).as_secs()
+        }
+        let status_text = match &self.status_message {
+            Some((msg, _)) => Line::raw(format!(" {}", msg)),
+            None => {
+                let mut spans = Vec::new();
+                if swap_risk {
+                    spans.push(Span::raw(" [SWAP RISK: MEMORY NOT LOCKED]"));
+                }
+                let (used, capacity) = (self.storage.used_bytes(), self.storage.capacity());
+                let percent = used * 100 / capacity.max(1);
+                if percent > CAPACITY_WARN_PERCENT {
+                    spans.push(Span::styled(
+                        format!(" [BUFFER {}% FULL]", percent),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+                let (words, read) = if self.status_format.contains("{words}")
+                    || self.status_format.contains("{read}")
+                {
+                    let generation = self.storage.generation();
+                    let words = match self.word_count {
+                        Some((g, n)) if g == generation => n,
+                        _ => {
+                            let n = content.split_whitespace().count();
+                            self.word_count = Some((generation, n));
+                            n
                         }
-                    })
-                    .map(|s| s.to_string())
-                    .unwrap_or("∞".into())
-            )
+                    };
+                    (words.to_string(), reading_time(words, self.reading_wpm))
+                } else {
+                    (String::new(), String::new())
+                };
+                let values = [
+                    ("line", (cur_line + 1).to_string()),
+                    ("col", (cur_col + 1).to_string()),
+                    ("idle", self.last_input.elapsed().as_secs().to_string()),
+                    (
+                        "idle_max",
+                        self.idle_timeout
+                            .map(|d| d.as_secs().to_string())
+                            .unwrap_or("∞".into()),
+                    ),
+                    (
+                        "ttl",
+                        self.ttl_expiry
+                            .map(|e| e.saturating_duration_since(Instant::now()).as_secs())
+                            .map(|s| s.to_string())
+                            .unwrap_or("∞".into()),
+                    ),
+                    ("words", words),
+                    ("read", read),
+                    (
+                        "mem",
+                        format!(
+                            "{} / {}",
+                            format_size(used as u64),
+                            format_size(capacity as u64)
+                        ),
+                    ),
+                ];
+                // {stealth} is styled on its own, so the pieces around it
+                // are formatted separately.
+                let stealth = if self.storage.is_encrypted() {
+                    "[STEALTH] "
+                } else {
+                    ""
+                };
+                for (i, part) in self.status_format.split("{stealth}").enumerate() {
+                    if i > 0 {
+                        spans.push(Span::styled(
+                            stealth,
+                            Style::default().fg(self.theme.stealth),
+                        ));
+                    }
+                    spans.push(Span::raw(format_status(part, &values)));
+                }
+                Line::from(spans)
+            }
         };
 
-        let status_bar = Paragraph::new(status_text)
-            .style(Style::default().fg(Color::Black).bg(Color::DarkGray));
-        frame.render_widget(status_bar, chunks[1]);
+        // Share of the TTL still left, shown as a gauge beside the status text.
+        let ttl_left = self.ttl_expiry.map(|expiry| {
+            let total = expiry
+                .saturating_duration_since(self.ttl_start)
+                .as_secs_f64();
+            let left = expiry
+                .saturating_duration_since(Instant::now())
+                .as_secs_f64();
+            if total > 0.0 {
+                (left / total).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        });
+        let status_chunks = Layout::horizontal([
+            Constraint::Min(1),
+            Constraint::Length(if ttl_left.is_some() { 16 } else { 0 }),
+        ])
+        .split(chunks[1]);
+
+        let status_bar = Paragraph::new(status_text).style(if swap_risk {
+            Style::default().fg(Color::White).bg(Color::Red)
+        } else {
+            Style::default()
+                .fg(self.theme.status_fg)
+                .bg(self.theme.status_bg)
+        });
+        frame.render_widget(status_bar, status_chunks[0]);
+
+        if let Some(left) = ttl_left {
+            let color = if left > 0.5 {
+                Color::Green
+            } else if left > 0.2 {
+                Color::Yellow
+            } else {
+                Color::Red
+            };
+            let gauge = Gauge::default()
+                .ratio(left)
+                .label(format!("TTL {:.0}%", left * 100.0))
+                .gauge_style(Style::default().fg(color).bg(self.theme.status_bg));
+            frame.render_widget(gauge, status_chunks[1]);
+        }
+
+        if self.input_mode == InputMode::Help {
+            self.draw_help(frame);
+        }
 
         // Render Popup if needed
-        if self.input_mode != InputMode::Normal {
+        if !matches!(self.input_mode, InputMode::Normal | InputMode::Help) {
             let block = Block::default()
                 .title(match self.input_mode {
-                    InputMode::EnterPath => " 1. Enter Filename (.amnesio) ",
+                    InputMode::EnterPath => " 1. Enter Filename (.amnesio, Ctrl+R: random) ",
+                    InputMode::ConfirmOverwrite => " File Exists ",
+                    InputMode::ConfirmClear => " Clear Note ",
                     InputMode::EnterPassword => " 2. Enter Password ",
+                    InputMode::ConfirmPassword => " 3. Confirm Password ",
+                    InputMode::GotoLine => " Go to Line ",
+                    InputMode::OpenPath => " 1. Open File (.amnesio, Tab: complete) ",
+                    InputMode::OpenPassword => " 2. Enter Password ",
+                    InputMode::InsertFile => " Insert Plaintext File (Tab: complete) ",
                     _ => "",
                 })
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan));
+                .border_style(Style::default().fg(self.theme.popup_border));
 
             let area = centered_rect(60, 20, frame.area());
             frame.render_widget(Clear, area); // Clear background
 
-            let input_text = match self.input_mode {
-                InputMode::EnterPath => self.path_buffer.clone(),
-                InputMode::EnterPassword => "*".repeat(self.password_buffer.len()),
-                _ => String::new(),
+            let input_text: Text = match self.input_mode {
+                InputMode::EnterPath | InputMode::OpenPath | InputMode::InsertFile => {
+                    let mut text = Text::from(self.path_buffer.clone());
+                    let candidates = self.path_candidates();
+                    if !candidates.is_empty() {
+                        text.push_line(
+                            Line::from(candidates.join("  "))
+                                .style(Style::default().fg(Color::DarkGray)),
+                        );
+                    }
+                    if self.input_mode == InputMode::InsertFile {
+                        text.push_line(
+                            Line::from("The file stays on disk, unencrypted")
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+                    }
+                    text
+                }
+                InputMode::GotoLine => self.goto_buffer.clone().into(),
+                InputMode::ConfirmClear => "Wipe this note? It has unsaved changes. (y/n)".into(),
+                InputMode::ConfirmOverwrite => {
+                    format!("Overwrite {}? (y/n)", self.save_path()).into()
+                }
+                InputMode::EnterPassword => {
+                    let mut text = Text::from(self.password_line(&self.password_buffer));
+                    if !self.password_buffer.is_empty() {
+                        text.push_line(strength_line(self.password_strength));
+                    }
+                    text
+                }
+                InputMode::ConfirmPassword => self.password_line(&self.confirm_buffer).into(),
+                InputMode::OpenPassword => {
+                    let mut text = Text::from(self.password_line(&self.password_buffer));
+                    if let Some(wait) = self.load_retry_remaining() {
+                        text.push_line(format!("(retry in {}s)", wait.as_secs() + 1));
+                    }
+                    text
+                }
+                _ => Text::default(),
             };
 
             let p = Paragraph::new(input_text)
@@ -408,56 +1946,852 @@ impl Editor {
             frame.render_widget(p, area);
         }
 
-        content.zeroize();
+        self.view_cache = view;
+        self.display_cache = display;
     }
 
-    fn render_markdown<'a>(&self, content: &'a str) -> Vec<Line<'a>> {
-        let mut lines = Vec::new();
-        for raw_line in content.lines() {
-            let mut spans = Vec::new();
-            if raw_line.starts_with("# ") {
-                spans.push(Span::styled(
-                    raw_line,
-                    Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD),
-                ));
-            } else if raw_line.starts_with("## ") {
-                spans.push(Span::styled(
-                    raw_line,
-                    Style::default()
-                        .fg(Color::Blue)
-                        .add_modifier(Modifier::BOLD),
-                ));
-            } else if raw_line.starts_with("### ") {
-                spans.push(Span::styled(
-                    raw_line,
-                    Style::default()
-                        .fg(Color::Green)
-                        .add_modifier(Modifier::BOLD),
-                ));
-            } else {
-                let parts: Vec<&str> = raw_line.split("**").collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if i % 2 == 1 {
-                        spans.push(Span::styled(
-                            *part,
-                            Style::default()
-                                .add_modifier(Modifier::BOLD)
-                                .fg(Color::Yellow),
-                        ));
-                    } else {
-                        spans.push(Span::raw(*part));
-                    }
+    /// The idle lock screen: no content, just the unlock prompt.
+    fn draw_locked(&self, frame: &mut Frame) {
+        frame.render_widget(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" amnesia [LOCKED] "),
+            frame.area(),
+        );
+
+        let mut text = Text::from(self.password_line(&self.password_buffer));
+        if let Some((msg, time)) = &self.status_message {
+            if time.elapsed() < self.status_timeout {
+                text.push_line(Line::styled(msg.as_str(), Style::default().fg(Color::Red)));
+            }
+        }
+
+        let area = centered_rect(60, 20, frame.area());
+        frame.render_widget(Clear, area);
+        let p = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .title(" Unlock: Enter Password ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.theme.popup_border)),
+            )
+            .alignment(Alignment::Center);
+        frame.render_widget(p, area);
+    }
+
+    /// Draws the line numbers down the left of `pane`, one per line on the
+    /// row where it starts. Wrapped continuation rows are left blank.
+    fn draw_gutter(
+        &self,
+        frame: &mut Frame,
+        pane: Rect,
+        gutter: u16,
+        content: &str,
+        wrap_width: Option<usize>,
+        cur_row: usize,
+    ) {
+        let rows = row_lines(content, self.tab_width, wrap_width);
+        let cur_line = rows
+            .iter()
+            .take(cur_row + 1)
+            .rev()
+            .find_map(|&line| line)
+            .unwrap_or(0);
+        let digits = gutter as usize - 1;
+        let height = pane.height.saturating_sub(2) as usize;
+        let labels: Vec<Line> = rows
+            .iter()
+            .skip(self.scroll_offset)
+            .take(height)
+            .map(|&line| match line {
+                Some(line) if line == cur_line => Line::styled(
+                    format!("{:>digits$} ", line + 1),
+                    Style::default().fg(self.theme.editor_fg),
+                ),
+                Some(line) => {
+                    let number = match self.line_numbers {
+                        LineNumbers::Relative => line.abs_diff(cur_line),
+                        _ => line + 1,
+                    };
+                    Line::raw(format!("{:>digits$} ", number))
+                }
+                None => Line::raw(""),
+            })
+            .collect();
+        let area = Rect::new(pane.x + 1, pane.y + 1, gutter, height as u16).intersection(pane);
+        frame.render_widget(
+            Paragraph::new(labels).style(
+                Style::default()
+                    .fg(self.theme.border)
+                    .bg(self.theme.editor_bg),
+            ),
+            area,
+        );
+    }
+
+    /// Underlines misspelled words in the editor pane. Words are only
+    /// rechecked when the text or its layout changes.
+    #[cfg(feature = "spellcheck")]
+    fn underline_misspelled(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        content: &str,
+        wrap_width: Option<usize>,
+    ) {
+        let Some(dictionary) = &self.dictionary else {
+            return;
+        };
+        let key = (self.storage.generation(), wrap_width, self.tab_width);
+        if self.spell_cache.as_ref().map(|c| c.key) != Some(key) {
+            let cells = misspelled_cells(dictionary, content, self.tab_width, wrap_width);
+            self.spell_cache = Some(SpellCache { key, cells });
+        }
+        let Some(cache) = &self.spell_cache else {
+            return;
+        };
+
+        let style = Style::default()
+            .add_modifier(Modifier::UNDERLINED)
+            .underline_color(Color::Red);
+        let (top, left) = (self.scroll_offset, self.h_scroll_offset as usize);
+        let height = area.height.saturating_sub(2) as usize;
+        let width = area.width.saturating_sub(2) as usize;
+        for &(row, col, cells) in &cache.cells {
+            if row < top || row >= top + height || col < left || col + cells > left + width {
+                continue;
+            }
+            let cell_area = Rect::new(
+                area.x + 1 + (col - left) as u16,
+                area.y + 1 + (row - top) as u16,
+                cells as u16,
+                1,
+            );
+            frame.buffer_mut().set_style(cell_area, style);
+        }
+    }
+
+    fn draw_help(&self, frame: &mut Frame) {
+        let tab_keys = self.key_bindings.goto_tab_range();
+        let entries: Vec<(String, &str)> = Action::ALL
+            .into_iter()
+            .filter_map(|action| match (action.tab_index(), &tab_keys) {
+                (Some(0), Some(keys)) => Some((keys.clone(), "Go to tab 1-9")),
+                (Some(_), Some(_)) => None,
+                _ => Some((
+                    self.key_bindings.key(action).to_string(),
+                    action.description(),
+                )),
+            })
+            .collect();
+        let key_width = entries
+            .iter()
+            .map(|(key, _)| key.width())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<Line> = entries
+            .into_iter()
+            .map(|(key, action)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!(" {:<width$}  ", key, width = key_width),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(action),
+                ])
+            })
+            .collect();
+
+        let area = centered_rect(70, 70, frame.area());
+        frame.render_widget(Clear, area);
+        let p = Paragraph::new(lines).block(
+            Block::default()
+                .title(" Keybindings (F1/Esc to close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(self.theme.popup_border)),
+        );
+        frame.render_widget(p, area);
+    }
+}
+
+/// Styles `content` for the Markdown view, one output line per input line:
+/// `#`-`######` headers, `**bold**`, `>` blockquotes (nested with more `>`) and
+/// `---`/`***`/`___` rules drawn `width` cells wide.
+pub fn render_markdown(content: &str, width: usize) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    for raw_line in content.lines() {
+        let mut spans = Vec::new();
+        if let Some(level) = header_level(raw_line) {
+            spans.push(Span::styled(raw_line, header_style(level)));
+        } else if is_rule(raw_line) {
+            spans.push(Span::styled(
+                "─".repeat(width),
+                Style::default().fg(Color::DarkGray),
+            ));
+        } else if raw_line.starts_with('>') {
+            let (depth, text) = quote_depth(raw_line);
+            let quote = Style::default()
+                .fg(Color::Gray)
+                .add_modifier(Modifier::DIM | Modifier::ITALIC);
+            for _ in 0..depth {
+                spans.push(Span::styled("│ ", Style::default().fg(Color::DarkGray)));
+            }
+            spans.extend(inline_spans(text, quote));
+        } else {
+            spans.extend(inline_spans(raw_line, Style::default()));
+        }
+        lines.push(Line::from(spans));
+    }
+    if content.ends_with('\n') {
+        lines.push(Line::from(""));
+    }
+    lines
+}
+
+/// Inline `**bold**`, `~~strikethrough~~` and `` `code` `` within a line,
+/// over `base`. Bold and strikethrough nest; code spans are taken literally.
+/// A marker without a closing partner is shown as written.
+fn inline_spans(text: &str, base: Style) -> Vec<Span<'_>> {
+    fn push<'a>(spans: &mut Vec<Span<'a>>, part: &'a str, style: Style) {
+        if !part.is_empty() {
+            spans.push(Span::styled(part, style));
+        }
+    }
+
+    let mut spans = Vec::new();
+    let (mut bold, mut strike) = (false, false);
+    let style = |bold: bool, strike: bool| {
+        let mut style = base;
+        if bold {
+            style = style.add_modifier(Modifier::BOLD).fg(Color::Yellow);
+        }
+        if strike {
+            style = style.add_modifier(Modifier::CROSSED_OUT);
+        }
+        style
+    };
+
+    let (mut start, mut i) = (0, 0);
+    while i < text.len() {
+        let rest = &text[i..];
+        if let Some(code) = rest.strip_prefix('`') {
+            if let Some(end) = code.find('`') {
+                push(&mut spans, &text[start..i], style(bold, strike));
+                let code_style = style(bold, strike)
+                    .fg(Color::LightMagenta)
+                    .bg(Color::Indexed(236));
+                push(&mut spans, &code[..end], code_style);
+                i += end + 2;
+                start = i;
+                continue;
+            }
+        } else if let Some(marker) = ["**", "~~"].into_iter().find(|m| rest.starts_with(m)) {
+            let open = if marker == "**" { bold } else { strike };
+            if open || rest[2..].contains(marker) {
+                push(&mut spans, &text[start..i], style(bold, strike));
+                if marker == "**" {
+                    bold = !bold;
+                } else {
+                    strike = !strike;
                 }
+                i += 2;
+                start = i;
+                continue;
+            }
+        }
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    push(&mut spans, &text[start..], style(bold, strike));
+    spans
+}
+
+/// The level of an ATX header line (`# ` to `###### `), if it is one.
+fn header_level(line: &str) -> Option<usize> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    ((1..=6).contains(&level) && line[level..].starts_with(' ')).then_some(level)
+}
+
+/// Header styles dim as the level gets deeper.
+fn header_style(level: usize) -> Style {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    match level {
+        1 => bold.fg(Color::Cyan),
+        2 => bold.fg(Color::Blue),
+        3 => bold.fg(Color::Green),
+        4 => bold.fg(Color::Gray),
+        5 => Style::default().fg(Color::Gray),
+        _ => Style::default()
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::ITALIC),
+    }
+}
+
+/// A thematic break: three or more `-`, `*` or `_`, optionally spaced out.
+fn is_rule(line: &str) -> bool {
+    let mut marks = line.chars().filter(|c| !c.is_whitespace());
+    let Some(first) = marks.next() else {
+        return false;
+    };
+    matches!(first, '-' | '*' | '_') && marks.clone().all(|c| c == first) && marks.count() >= 2
+}
+
+/// Nesting depth of a blockquote line (`>`, `>>` or `> >`) and its text.
+fn quote_depth(line: &str) -> (usize, &str) {
+    let mut depth = 0;
+    let mut rest = line;
+    while let Some(stripped) = rest.strip_prefix('>') {
+        depth += 1;
+        rest = stripped.strip_prefix(' ').unwrap_or(stripped);
+    }
+    (depth, rest)
+}
+
+/// Line and terminal column of the byte offset `cursor`.
+/// Columns are display cells, so wide (CJK, emoji) graphemes count as two
+/// and tabs advance to the next multiple of `tab_width`.
+pub fn display_position(content: &str, cursor: usize, tab_width: usize) -> (usize, usize) {
+    visual_position(content, cursor, tab_width, None)
+}
+
+/// Like `display_position`, but counting visual rows when lines are
+/// soft-wrapped at `wrap_width` cells.
+pub fn visual_position(
+    content: &str,
+    cursor: usize,
+    tab_width: usize,
+    wrap_width: Option<usize>,
+) -> (usize, usize) {
+    let mut pos = None;
+    let end = layout(content, tab_width, wrap_width, |idx, _, row, col, _| {
+        if idx >= cursor {
+            pos = Some((row, col));
+            return false;
+        }
+        true
+    });
+    match (pos.unwrap_or(end), wrap_width) {
+        // A cursor just past a full row is drawn at the start of the next one.
+        ((row, col), Some(width)) if col >= width => (row + 1, 0),
+        (p, _) => p,
+    }
+}
+
+/// Converts CRLF and lone CR line endings to LF, the only line break the
+/// editor works with. The result never reallocates, so no stray copies
+/// of the text are left behind.
+pub fn normalize_newlines(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\r' {
+            chars.next_if_eq(&'\n');
+            out.push('\n');
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+/// Converts LF line endings to CRLF, for saving.
+pub fn to_crlf(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.matches('\n').count());
+    for ch in text.chars() {
+        if ch == '\n' {
+            out.push('\r');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Replaces tabs with spaces up to the next tab stop, for rendering.
+pub fn expand_tabs(content: &str, tab_width: usize) -> String {
+    layout_text(content, tab_width, None)
+}
+
+/// Renders `content` for the terminal: tabs become spaces and, with a
+/// `wrap_width`, long lines are broken into rows of at most that many cells.
+pub fn layout_text(content: &str, tab_width: usize, wrap_width: Option<usize>) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut last_row = 0;
+    layout(content, tab_width, wrap_width, |_, g, row, _, cells| {
+        if row != last_row && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        last_row = row;
+        if g == "\t" {
+            out.extend(std::iter::repeat_n(' ', cells));
+        } else {
+            out.push_str(g);
+        }
+        true
+    });
+    out
+}
+
+/// Misspelled words found for one version of the text and its layout.
+#[cfg(feature = "spellcheck")]
+struct SpellCache {
+    key: (u64, Option<usize>, usize), // Generation, wrap width, tab width
+    cells: Vec<(usize, usize, usize)>, // Row, column and width of each flagged grapheme
+}
+
+/// Screen positions of the graphemes of every misspelled word in `content`.
+#[cfg(feature = "spellcheck")]
+fn misspelled_cells(
+    dictionary: &crate::spell::Dictionary,
+    content: &str,
+    tab_width: usize,
+    wrap_width: Option<usize>,
+) -> Vec<(usize, usize, usize)> {
+    let flagged: Vec<(usize, usize)> = crate::spell::words(content)
+        .into_iter()
+        .filter(|&(start, end)| dictionary.is_misspelled(&content[start..end]))
+        .collect();
+    let mut cells = Vec::new();
+    let mut next = flagged.iter().peekable();
+    layout(content, tab_width, wrap_width, |_, g, row, col, width| {
+        let offset = g.as_ptr() as usize - content.as_ptr() as usize;
+        while next.peek().is_some_and(|&&(_, end)| end <= offset) {
+            next.next();
+        }
+        match next.peek() {
+            Some(&&(start, _)) if offset >= start => {
+                cells.push((row, col, width));
+                true
             }
-            lines.push(Line::from(spans));
+            Some(_) => true,
+            None => false,
+        }
+    });
+    cells
+}
+
+/// Lays out `content` like `layout_text`, with spaces drawn as `·`, tabs as
+/// `→` and trailing whitespace on a red background. Text is borrowed from
+/// `content` rather than copied, so no extra plaintext is left around.
+fn whitespace_lines<'a>(
+    content: &'a str,
+    tab_width: usize,
+    wrap_width: Option<usize>,
+) -> Vec<Line<'a>> {
+    let marker = Style::default().fg(Color::DarkGray);
+    let trailing = Style::default().fg(Color::White).bg(Color::Red);
+    // Byte offset where each logical line's trailing whitespace starts.
+    let mut trailing_from = Vec::new();
+    let mut offset = 0;
+    for line in content.split('\n') {
+        let text = line.strip_suffix('\r').unwrap_or(line);
+        trailing_from.push(offset + text.trim_end_matches([' ', '\t']).len());
+        offset += line.len() + 1;
+    }
+
+    let mut lines = vec![Line::default()];
+    let (mut last_row, mut logical) = (0, 0);
+    let mut run: Option<(usize, usize)> = None; // Pending plain text, as a byte range
+    let flush = |lines: &mut Vec<Line<'a>>, run: &mut Option<(usize, usize)>| {
+        if let Some((start, end)) = run.take() {
+            lines
+                .last_mut()
+                .unwrap()
+                .push_span(Span::raw(&content[start..end]));
+        }
+    };
+    layout(content, tab_width, wrap_width, |_, g, row, _, cells| {
+        let start = g.as_ptr() as usize - content.as_ptr() as usize;
+        if row != last_row {
+            flush(&mut lines, &mut run);
+            if lines.len() <= row {
+                lines.push(Line::default());
+            }
+            last_row = row;
+        }
+        if g == "\n" || g == "\r\n" {
+            logical += 1;
+            return true;
         }
-        if content.ends_with('\n') {
-            lines.push(Line::from(""));
+        if g != " " && g != "\t" {
+            run = match run {
+                Some((s, _)) => Some((s, start + g.len())),
+                None => Some((start, start + g.len())),
+            };
+            return true;
         }
+
+        flush(&mut lines, &mut run);
+        let style = if start >= trailing_from[logical] {
+            trailing
+        } else {
+            marker
+        };
+        let symbol = if g == " " {
+            "·".to_string()
+        } else {
+            format!("→{}", " ".repeat(cells.saturating_sub(1)))
+        };
         lines
+            .last_mut()
+            .unwrap()
+            .push_span(Span::styled(symbol, style));
+        true
+    });
+    flush(&mut lines, &mut run);
+    lines
+}
+
+/// Walks `content` grapheme by grapheme, tracking the visual row and column
+/// each one is drawn at. With a `wrap_width`, a grapheme that would overflow
+/// the row starts a new one (tabs are cut short at the edge instead).
+/// `visit(byte_offset, grapheme, row, col, cells)` returns false to stop early.
+/// Returns the position just past the last grapheme.
+fn layout<'a>(
+    content: &'a str,
+    tab_width: usize,
+    wrap_width: Option<usize>,
+    mut visit: impl FnMut(usize, &'a str, usize, usize, usize) -> bool,
+) -> (usize, usize) {
+    let (mut row, mut col, mut idx) = (0, 0, 0);
+    for g in content.graphemes(true) {
+        let newline = g == "\n" || g == "\r\n";
+        let mut cells = if newline {
+            0
+        } else {
+            grapheme_width(g, col, tab_width)
+        };
+        if let Some(width) = wrap_width {
+            if g == "\t" && col < width {
+                cells = cells.min(width - col);
+            } else if !newline && col > 0 && col + cells > width {
+                row += 1;
+                col = 0;
+                cells = grapheme_width(g, col, tab_width).min(width);
+            }
+        }
+        if !visit(idx, g, row, col, cells) {
+            return (row, col);
+        }
+        idx += g.len();
+        if newline {
+            row += 1;
+            col = 0;
+        } else {
+            col += cells;
+        }
+    }
+    (row, col)
+}
+
+/// The line each visual row of `content` starts, or `None` for a row that
+/// continues a wrapped line.
+fn row_lines(content: &str, tab_width: usize, wrap_width: Option<usize>) -> Vec<Option<usize>> {
+    let mut rows = vec![Some(0)];
+    let mut line = 0;
+    let mut newline = false;
+    layout(content, tab_width, wrap_width, |_, g, row, _, _| {
+        if row >= rows.len() {
+            rows.push(newline.then_some(line));
+        }
+        newline = g == "\n" || g == "\r\n";
+        if newline {
+            line += 1;
+        }
+        true
+    });
+    if newline {
+        rows.push(Some(line));
+    }
+    rows
+}
+
+/// Cells taken by `g` when drawn at column `col`.
+fn grapheme_width(g: &str, col: usize, tab_width: usize) -> usize {
+    if g == "\t" {
+        let tab_width = tab_width.max(1);
+        tab_width - col % tab_width
+    } else {
+        g.width()
+    }
+}
+
+/// Masks every drawn character in `area` with •, keeping the text's shape.
+fn redact(buffer: &mut Buffer, area: Rect) {
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            let cell = &mut buffer[(x, y)];
+            if !cell.symbol().trim().is_empty() {
+                cell.set_symbol("•");
+            }
+        }
+    }
+}
+
+/// Rows `top..top + height` of `text`, which has one row per line.
+fn visible_rows(text: &str, top: usize, height: usize) -> &str {
+    let start = match top {
+        0 => 0,
+        _ => text
+            .match_indices('\n')
+            .nth(top - 1)
+            .map_or(text.len(), |(i, _)| i + 1),
+    };
+    let rest = &text[start..];
+    let end = match height {
+        0 => 0,
+        _ => rest
+            .match_indices('\n')
+            .nth(height - 1)
+            .map_or(rest.len(), |(i, _)| i),
+    };
+    &rest[..end]
+}
+
+/// Rows `top..top + height` of already split `lines`.
+fn visible_lines(lines: Vec<Line<'_>>, top: usize, height: usize) -> Vec<Line<'_>> {
+    lines.into_iter().skip(top).take(height).collect()
+}
+
+/// The char boundary at or before byte offset `at`, clamped to the end.
+fn floor_char_boundary(content: &str, at: usize) -> usize {
+    let mut at = at.min(content.len());
+    while !content.is_char_boundary(at) {
+        at -= 1;
+    }
+    at
+}
+
+/// Byte offset of the grapheme boundary after `at` (clamped to the end).
+fn next_grapheme(content: &str, at: usize) -> usize {
+    let at = floor_char_boundary(content, at);
+    content[at..]
+        .graphemes(true)
+        .next()
+        .map_or(at, |g| at + g.len())
+}
+
+/// Byte offset of the grapheme boundary before `at` (clamped to 0).
+fn prev_grapheme(content: &str, at: usize) -> usize {
+    let at = floor_char_boundary(content, at);
+    content[..at]
+        .graphemes(true)
+        .next_back()
+        .map_or(0, |g| at - g.len())
+}
+
+/// Byte offset of the start of the word before `at`, skipping spaces and
+/// tabs but not line breaks. Words follow Unicode word boundaries, so
+/// "don't" is one word and punctuation runs are their own.
+fn prev_word_start(content: &str, at: usize) -> usize {
+    let at = floor_char_boundary(content, at);
+    let mut segments = content[..at].split_word_bound_indices().rev().peekable();
+    let mut start = at;
+    while let Some(&(i, _)) = segments
+        .peek()
+        .filter(|(_, s)| s.chars().all(|c| c == ' ' || c == '\t'))
+    {
+        start = i;
+        segments.next();
+    }
+    match segments.next() {
+        Some((_, "\n" | "\r\n")) if start < at => start,
+        Some((i, _)) => i,
+        None => start,
+    }
+}
+
+/// Byte offsets at which each line of `content` starts.
+fn line_starts(content: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+    starts
+}
+
+/// The line holding byte offset `at`, given the `line_starts` of the text.
+fn line_of(starts: &[usize], at: usize) -> usize {
+    starts.partition_point(|&start| start <= at).max(1) - 1
+}
+
+/// The text of line `line`, without its line break.
+fn line_text<'a>(content: &'a str, starts: &[usize], line: usize) -> &'a str {
+    let end = starts.get(line + 1).map_or(content.len(), |&next| next - 1);
+    &content[starts[line]..end]
+}
+
+/// Estimated reading time for `words` at `wpm`, such as "3 min"; at least
+/// a minute for any text, and empty for an empty note.
+pub fn reading_time(words: usize, wpm: u32) -> String {
+    if words == 0 {
+        return String::new();
     }
+    let minutes = (words as f64 / wpm.max(1) as f64).round().max(1.0);
+    format!("{} min", minutes)
+}
+
+/// Entries in the directory of a partly typed `path` whose names start with
+/// its last component, sorted, with a trailing separator on directories.
+/// Hidden entries are only offered once a leading '.' has been typed.
+pub fn path_completions(path: &str) -> Vec<String> {
+    let (dir, partial) = match path.rfind(std::path::is_separator) {
+        Some(i) => (&path[..=i], &path[i + 1..]),
+        None => ("", path),
+    };
+    let entries = match std::fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let mut name = e.file_name().into_string().ok()?;
+            if !name.starts_with(partial) || (name.starts_with('.') && !partial.starts_with('.')) {
+                return None;
+            }
+            if e.path().is_dir() {
+                name.push(std::path::MAIN_SEPARATOR);
+            }
+            Some(name)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// Checks that the directory a note would be saved to exists and is
+/// writable, returning a message for the status bar if not.
+fn check_save_dir(path: &Path) -> Result<(), String> {
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    match std::fs::metadata(dir) {
+        Ok(m) if m.is_dir() => {}
+        Ok(_) => return Err(format!("Not a directory: {}", dir.display())),
+        Err(_) => return Err(format!("Directory not found: {}", dir.display())),
+    }
+    if !dir_writable(dir) {
+        return Err(format!("Directory not writable: {}", dir.display()));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn dir_writable(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    match std::ffi::CString::new(dir.as_os_str().as_bytes()) {
+        Ok(c_path) => unsafe { libc::access(c_path.as_ptr(), libc::W_OK) == 0 },
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn dir_writable(dir: &Path) -> bool {
+    std::fs::metadata(dir).is_ok_and(|m| !m.permissions().readonly())
+}
+
+/// Human-readable size: "512 B", "1.2 KB", "3.4 MB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// The OSC 52 escape sequence that asks the terminal to put `text` on the
+/// system clipboard. Works over SSH, with no native clipboard access.
+pub fn osc52_sequence(text: &str) -> String {
+    let mut encoded = BASE64_STANDARD.encode(text);
+    let sequence = format!("\x1b]52;c;{}\x07", encoded);
+    encoded.zeroize();
+    sequence
+}
+
+/// Replaces `{name}` tokens in `template` with their values.
+/// Unknown tokens and unmatched braces are kept as written.
+pub fn format_status(template: &str, values: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find('}') {
+            Some(close) => {
+                let name = &after[..close];
+                match values.iter().find(|(token, _)| *token == name) {
+                    Some((_, value)) => out.push_str(value),
+                    None => out.push_str(&rest[open..open + close + 2]),
+                }
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push_str(&rest[open..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The key failed opens are counted under: the canonical path, so every
+/// spelling of a file shares one count, or the path as typed if it can't
+/// be resolved.
+fn attempts_key(path: &str) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Estimated password strength from 0 (trivially guessable) to 4 (very
+/// strong), from its length and the character classes it draws on. Runs
+/// on the secret in place: nothing is copied to the heap where it couldn't
+/// be wiped. A run of one repeated character only counts once.
+pub fn password_score(password: &str) -> u8 {
+    let (mut lower, mut upper, mut digit, mut symbol, mut other) = (0, 0, 0, 0, 0);
+    let mut length = 0u32;
+    let mut previous = None;
+    for c in password.chars() {
+        match c {
+            'a'..='z' => lower = 26,
+            'A'..='Z' => upper = 26,
+            '0'..='9' => digit = 10,
+            c if c.is_ascii() => symbol = 33,
+            _ => other = 100,
+        }
+        if previous != Some(c) {
+            length += 1;
+        }
+        previous = Some(c);
+    }
+    let pool: u32 = lower + upper + digit + symbol + other;
+    if pool == 0 {
+        return 0;
+    }
+    let bits = f64::from(length) * f64::from(pool).log2();
+    match bits {
+        b if b < 28.0 => 0,
+        b if b < 45.0 => 1,
+        b if b < 60.0 => 2,
+        b if b < 80.0 => 3,
+        _ => 4,
+    }
+}
+
+fn strength_line(score: u8) -> Line<'static> {
+    let (label, color) = match score {
+        0 | 1 => ("weak", Color::Red),
+        2 => ("fair", Color::Yellow),
+        _ => ("strong", Color::Green),
+    };
+    let bar = "█".repeat(score as usize + 1) + &"░".repeat(4 - score as usize);
+    Line::from(Span::styled(
+        format!("{} {}", bar, label),
+        Style::default().fg(color),
+    ))
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
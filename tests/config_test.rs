@@ -0,0 +1,293 @@
+use amnesia::config::Config;
+
+fn config_with_idle(idle: Option<f64>) -> Config {
+    Config {
+        idle,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_cli_idle_wins_over_everything() {
+    let config = config_with_idle(Some(300.0));
+    assert_eq!(config.resolve_idle(Some(10.0), None), Some(10.0));
+    assert_eq!(config.resolve_idle(Some(10.0), Some(5.0)), Some(10.0));
+    assert_eq!(
+        config_with_idle(None).resolve_idle(Some(10.0), None),
+        Some(10.0)
+    );
+}
+
+#[test]
+fn test_cli_ttl_disables_idle() {
+    assert_eq!(
+        config_with_idle(Some(300.0)).resolve_idle(None, Some(5.0)),
+        None
+    );
+    assert_eq!(config_with_idle(None).resolve_idle(None, Some(5.0)), None);
+}
+
+#[test]
+fn test_falls_back_to_config_then_default() {
+    assert_eq!(
+        config_with_idle(Some(42.0)).resolve_idle(None, None),
+        Some(42.0)
+    );
+    assert_eq!(config_with_idle(None).resolve_idle(None, None), None);
+    assert_eq!(Config::default().resolve_idle(None, None), Some(300.0));
+}
+
+#[test]
+fn test_load_from_explicit_path() {
+    let path = std::env::temp_dir().join("amnesia_test_config_load_from.toml");
+    std::fs::write(&path, "ttl = 5.0\ntab_width = 2\n").unwrap();
+    let config = Config::load_from(&path, None).expect("Valid config should load");
+    assert_eq!(config.ttl, Some(5.0));
+    assert_eq!(config.tab_width, Some(2));
+
+    std::fs::write(&path, "ttl = \"soon\"\n").unwrap();
+    assert!(Config::load_from(&path, None).is_err());
+
+    std::fs::remove_file(&path).ok();
+    assert!(Config::load_from(&path, None).is_err());
+}
+
+#[test]
+fn test_write_default_creates_a_loadable_file_once() {
+    let dir = std::env::temp_dir().join("amnesia_test_init_config");
+    let path = dir.join("config.toml");
+    std::fs::remove_dir_all(&dir).ok();
+
+    Config::write_default(&path).expect("Write failed");
+    let config = Config::load_from(&path, None).expect("Default config should load");
+    assert_eq!(config.idle, Config::default().idle);
+
+    std::fs::write(&path, "ttl = 5.0\n").unwrap();
+    let err = Config::write_default(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "ttl = 5.0\n");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_effective_config_fills_defaults_and_notes_disabled_timers() {
+    let config = Config {
+        ttl: None,
+        tab_width: Some(2),
+        ..Config::default()
+    }
+    .with_defaults();
+    assert_eq!(config.tab_width, Some(2));
+    assert_eq!(config.reading_wpm, Some(200));
+    assert_eq!(config.autosave, None);
+
+    let toml = config.to_toml().expect("Config should serialize");
+    assert!(toml.starts_with("# ttl = off\n"));
+    assert!(toml.contains("idle = 300.0\n"));
+    let reparsed: Config = toml::from_str(&toml).expect("Output should parse back");
+    assert_eq!(reparsed.ttl, None);
+    assert_eq!(reparsed.tab_width, Some(2));
+}
+
+#[test]
+fn test_profiles_override_top_level_values() {
+    let content = r##"
+ttl = 100.0
+idle = 300.0
+tab_width = 2
+
+[theme]
+editor_fg = "white"
+editor_bg = "black"
+
+[profiles.work]
+ttl = 5.0
+idle = 30.0
+
+[profiles.work.theme]
+editor_bg = "#1e1e2e"
+
+[profiles.journal]
+idle = 1800.0
+"##;
+
+    let top = Config::parse(content, None).unwrap();
+    assert_eq!((top.ttl, top.idle), (Some(100.0), Some(300.0)));
+
+    let work = Config::parse(content, Some("work")).unwrap();
+    assert_eq!((work.ttl, work.idle), (Some(5.0), Some(30.0)));
+    assert_eq!(work.tab_width, Some(2));
+    let theme = work.theme.unwrap();
+    assert_eq!(theme.editor_fg.as_deref(), Some("white"));
+    assert_eq!(theme.editor_bg.as_deref(), Some("#1e1e2e"));
+
+    let journal = Config::parse(content, Some("journal")).unwrap();
+    assert_eq!((journal.ttl, journal.idle), (Some(100.0), Some(1800.0)));
+
+    assert!(Config::parse(content, Some("holiday")).is_err());
+    assert!(Config::parse("ttl = 1.0\n", Some("work")).is_err());
+}
+
+#[test]
+fn test_validate_drops_nonsensical_values() {
+    let mut config = Config {
+        ttl: Some(-5.0),
+        idle: Some(f64::NAN),
+        key_rotation: Some(f64::INFINITY),
+        tab_width: Some(0),
+        min_password_score: Some(9),
+        ..Config::default()
+    };
+    let warnings = config.validate();
+    assert_eq!(warnings.len(), 5);
+    assert_eq!(config.ttl, None);
+    assert_eq!(config.idle, None);
+    assert_eq!(config.key_rotation, None);
+    assert_eq!(config.tab_width, None);
+    assert_eq!(config.min_password_score, None);
+}
+
+#[test]
+fn test_validate_treats_zero_as_disabled() {
+    let mut config = Config {
+        ttl: Some(0.0),
+        idle: Some(0.0),
+        ..Config::default()
+    };
+    assert!(config.validate().is_empty());
+    assert_eq!(config.ttl, None);
+    assert_eq!(config.idle, None);
+}
+
+#[test]
+fn test_validate_keeps_sensible_values() {
+    let mut config = Config {
+        ttl: Some(10.0),
+        idle: Some(30.0),
+        tab_width: Some(8),
+        min_password_score: Some(3),
+        ..Config::default()
+    };
+    assert!(config.validate().is_empty());
+    assert_eq!(config.ttl, Some(10.0));
+    assert_eq!(config.idle, Some(30.0));
+    assert_eq!(config.tab_width, Some(8));
+    assert_eq!(config.min_password_score, Some(3));
+}
+
+#[test]
+fn test_env_overrides_config_but_not_cli() {
+    let mut config = Config {
+        ttl: Some(100.0),
+        idle: Some(300.0),
+        ..Config::default()
+    };
+    let warnings = config.apply_env(|name| match name {
+        "AMNESIA_TTL" => Some("15".into()),
+        "AMNESIA_IDLE" => Some("soon".into()),
+        _ => None,
+    });
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(config.ttl, Some(15.0));
+    assert_eq!(config.idle, Some(300.0));
+
+    assert_eq!(config.resolve_idle(Some(5.0), None), Some(5.0));
+}
+
+#[test]
+fn test_env_values_are_validated() {
+    let mut config = Config::default();
+    let warnings = config.apply_env(|name| (name == "AMNESIA_IDLE").then(|| "-1".into()));
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(config.idle, None);
+}
+
+#[test]
+fn test_env_does_not_repeat_config_warnings() {
+    let mut config = Config::parse("[keys]\nopen = \"ctrl+s\"\n", None).unwrap();
+    assert_eq!(config.validate().len(), 1);
+    let warnings = config.apply_env(|name| (name == "AMNESIA_TTL").then(|| "20".into()));
+    assert!(warnings.is_empty());
+    assert_eq!(config.ttl, Some(20.0));
+}
+
+#[test]
+fn test_minutes_until_wall_clock_time() {
+    use amnesia::config::minutes_until;
+    use chrono::NaiveTime;
+
+    let now = NaiveTime::from_hms_opt(16, 0, 0).unwrap();
+    assert_eq!(minutes_until("17:30", now), Some(90.0));
+    // Already passed today: tomorrow.
+    assert_eq!(minutes_until("15:00", now), Some(23.0 * 60.0));
+    assert_eq!(minutes_until("16:00", now), Some(24.0 * 60.0));
+    assert_eq!(minutes_until("25:00", now), None);
+    assert_eq!(minutes_until("soon", now), None);
+}
+
+#[test]
+fn test_theme_colors_parse_over_defaults() {
+    use amnesia::tui_app::Theme;
+    use ratatui::style::Color;
+
+    let mut config: Config = toml::from_str(
+        "[theme]\neditor_fg = \"black\"\neditor_bg = \"#fdf6e3\"\nborder = \"42\"\nstealth = \"not-a-color\"\n",
+    )
+    .unwrap();
+    let warnings = config.validate();
+    assert_eq!(warnings.len(), 1);
+
+    let theme = config.theme.unwrap().to_theme();
+    assert_eq!(theme.editor_fg, Color::Black);
+    assert_eq!(theme.editor_bg, Color::Rgb(0xfd, 0xf6, 0xe3));
+    assert_eq!(theme.border, Color::Indexed(42));
+    assert_eq!(theme.stealth, Theme::default().stealth);
+    assert_eq!(theme.status_bg, Theme::default().status_bg);
+}
+
+#[test]
+fn test_keys_table_remaps_actions() {
+    use amnesia::keys::{Action, KeySpec};
+    let mut config: Config = toml::from_str(
+        r#"
+panic_key = "f12"
+
+[keys]
+save = "alt+s"
+quit = "ctrl+q"
+open = "o"
+nonsense = "ctrl+n"
+wrap = "ctrl+xy"
+"#,
+    )
+    .unwrap();
+    let warnings = config.validate();
+    assert_eq!(warnings.len(), 3, "{:?}", warnings);
+
+    let bindings = config.key_bindings();
+    assert_eq!(bindings.key(Action::Save), KeySpec::parse("alt+s").unwrap());
+    assert_eq!(
+        bindings.key(Action::Quit),
+        KeySpec::parse("ctrl+q").unwrap()
+    );
+    assert_eq!(
+        bindings.key(Action::PanicWipe),
+        KeySpec::parse("f12").unwrap()
+    );
+    // Rejected entries keep their defaults.
+    assert_eq!(
+        bindings.key(Action::Open),
+        KeySpec::parse("ctrl+o").unwrap()
+    );
+    assert_eq!(
+        bindings.key(Action::WrapToggle),
+        KeySpec::parse("alt+z").unwrap()
+    );
+
+    // Binding two actions to one key is reported.
+    let mut config: Config = toml::from_str("[keys]\nopen = \"ctrl+s\"\n").unwrap();
+    let warnings = config.validate();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("keys.save and keys.open"));
+}
@@ -0,0 +1,44 @@
+use amnesia::persistence::PersistenceError;
+use amnesia::SecureDocument;
+
+#[test]
+fn test_insert_delete_and_read_back() {
+    for stealth in [false, true] {
+        let mut document = SecureDocument::new_lenient(stealth);
+        assert!(document.is_empty());
+
+        document.insert(0, "hello world");
+        document.insert(5, ",");
+        document.delete(6..12);
+        document.insert(100, "€!");
+        assert_eq!(document.with_text(str::to_string), "hello,€!");
+        assert_eq!(document.len(), 10);
+
+        // Offsets inside the euro sign move back to its start.
+        document.delete(7..8);
+        assert_eq!(document.with_text(str::to_string), "hello,€!");
+
+        document.wipe();
+        assert!(document.is_empty());
+    }
+}
+
+#[test]
+fn test_save_and_open_round_trip() {
+    let path = "test_document_round_trip.amnesio";
+    let password = "supersecretpassword888";
+
+    let mut document = SecureDocument::new_lenient(true);
+    document.set_text("line one\nline two");
+    let info = document.save(path, password).unwrap();
+    assert_eq!(info.version, amnesia::persistence::CURRENT_VERSION);
+
+    let opened = SecureDocument::open(path, password, false).unwrap();
+    assert_eq!(opened.with_text(str::to_string), "line one\nline two");
+    assert!(matches!(
+        SecureDocument::open(path, "wrongpassword123", false),
+        Err(PersistenceError::DecryptionFailed)
+    ));
+
+    std::fs::remove_file(path).ok();
+}
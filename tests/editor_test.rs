@@ -0,0 +1,1438 @@
+use amnesia::persistence;
+use amnesia::tui_app::{format_size, format_status, password_score, Editor, InputMode};
+use std::path::Path;
+
+fn type_str(editor: &mut Editor, s: &str) {
+    for c in s.chars() {
+        editor.handle_input(c);
+    }
+}
+
+#[test]
+fn test_open_destroys_file_after_max_attempts() {
+    let path = "test_editor_open_attempts.amnesio";
+    std::fs::remove_file(path).ok();
+    persistence::save_encrypted(path, "secret", "correcthorse", true).expect("Save failed");
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.max_load_attempts = Some(2);
+
+    editor.enter_open_mode();
+    type_str(&mut editor, path);
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::OpenPassword);
+
+    type_str(&mut editor, "wrongpass");
+    editor.handle_newline();
+    assert_eq!(editor.failed_load_attempts(path), 1);
+    assert!(Path::new(path).exists());
+
+    // Skip the backoff delay.
+    editor.load_retry_at = None;
+    type_str(&mut editor, "wrongpass");
+    editor.handle_newline();
+    assert!(!Path::new(path).exists());
+    assert!(editor.input_mode == InputMode::Normal);
+}
+
+#[test]
+fn test_other_path_spellings_share_the_attempt_count() {
+    let path = "test_editor_open_spellings.amnesio";
+    std::fs::remove_file(path).ok();
+    persistence::save_encrypted(path, "secret", "correcthorse", true).expect("Save failed");
+    let absolute = std::env::current_dir().unwrap().join(path);
+    let spellings = [
+        path.to_string(),
+        format!("./{}", path),
+        absolute.to_str().unwrap().to_string(),
+    ];
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.max_load_attempts = Some(3);
+    for (i, spelling) in spellings.iter().enumerate() {
+        editor.load_retry_at = None;
+        editor.exit_popup();
+        editor.enter_open_mode();
+        type_str(&mut editor, spelling);
+        editor.handle_newline();
+        type_str(&mut editor, "wrongpass");
+        editor.handle_newline();
+        if i < 2 {
+            assert_eq!(editor.failed_load_attempts(path), i as u32 + 1);
+        }
+    }
+    assert!(!Path::new(path).exists());
+}
+
+#[test]
+fn test_alternating_files_keeps_each_attempt_count() {
+    let paths = [
+        "test_editor_open_alternate_a.amnesio",
+        "test_editor_open_alternate_b.amnesio",
+    ];
+    for path in paths {
+        std::fs::remove_file(path).ok();
+        persistence::save_encrypted(path, "secret", "correcthorse", true).expect("Save failed");
+    }
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.max_load_attempts = Some(2);
+    for round in 0..2 {
+        for path in paths {
+            editor.load_retry_at = None;
+            editor.exit_popup();
+            editor.enter_open_mode();
+            type_str(&mut editor, path);
+            editor.handle_newline();
+            type_str(&mut editor, "wrongpass");
+            editor.handle_newline();
+            if round == 0 {
+                assert_eq!(editor.failed_load_attempts(path), 1);
+            }
+        }
+    }
+    for path in paths {
+        assert!(!Path::new(path).exists());
+    }
+}
+
+#[test]
+fn test_open_loads_content_and_resets_attempts() {
+    let path = "test_editor_open_success.amnesio";
+    std::fs::remove_file(path).ok();
+    persistence::save_encrypted(path, "hello", "correcthorse", true).expect("Save failed");
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.max_load_attempts = Some(3);
+
+    editor.enter_open_mode();
+    type_str(&mut editor, path);
+    editor.handle_newline();
+    type_str(&mut editor, "wrongpass");
+    editor.handle_newline();
+    editor.load_retry_at = None;
+    type_str(&mut editor, "correcthorse");
+    editor.handle_newline();
+
+    assert_eq!(editor.storage.to_string(), "hello");
+    assert_eq!(editor.failed_load_attempts(path), 0);
+
+    persistence::shred_file(path).ok();
+}
+
+#[test]
+fn test_open_retry_is_delayed_after_wrong_password() {
+    let path = "test_editor_open_backoff.amnesio";
+    std::fs::remove_file(path).ok();
+    persistence::save_encrypted(path, "hello", "correcthorse", true).expect("Save failed");
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.enter_open_mode();
+    type_str(&mut editor, path);
+    editor.handle_newline();
+    type_str(&mut editor, "wrongpass");
+    editor.handle_newline();
+    assert!(editor.load_retry_remaining().is_some());
+
+    // An immediate retry is refused and keeps the popup open.
+    type_str(&mut editor, "correcthorse");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::OpenPassword);
+    assert_eq!(editor.storage.to_string(), "");
+
+    persistence::shred_file(path).ok();
+}
+
+const FAMILY: &str = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+const E_COMBINING: &str = "e\u{0301}";
+
+#[test]
+fn test_save_requires_min_password_score() {
+    let path = "test_editor_weak_password.amnesio";
+    std::fs::remove_file(path).ok();
+    assert!(password_score("password") < 2);
+    assert_eq!(password_score("aaaaaaaaaaaaaaaaaaaa"), 0);
+    assert_eq!(password_score(""), 0);
+    assert!(password_score("violet-tractor-umbrella-91") >= 3);
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.read_only_output = false;
+    editor.min_password_score = Some(3);
+    type_str(&mut editor, "note");
+
+    editor.enter_save_mode();
+    type_str(&mut editor, path);
+    editor.handle_newline();
+    type_str(&mut editor, "password");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::EnterPassword);
+    assert!(!Path::new(path).exists());
+
+    editor.password_buffer.clear();
+    type_str(&mut editor, "violet-tractor-umbrella-91");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::ConfirmPassword);
+    type_str(&mut editor, "violet-tractor-umbrella-91");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::Normal);
+    assert!(Path::new(path).exists());
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_save_requires_matching_confirmation() {
+    let path = "test_editor_confirm_password.amnesio";
+    std::fs::remove_file(path).ok();
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.read_only_output = false;
+    type_str(&mut editor, "note");
+
+    editor.enter_save_mode();
+    type_str(&mut editor, path);
+    editor.handle_newline();
+    type_str(&mut editor, "correcthorse");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::ConfirmPassword);
+    assert!(!Path::new(path).exists());
+
+    type_str(&mut editor, "correcthorsf");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::EnterPassword);
+    assert!(editor.password_buffer.is_empty());
+    assert!(editor.confirm_buffer.is_empty());
+    assert!(!Path::new(path).exists());
+
+    type_str(&mut editor, "correcthorse");
+    editor.handle_newline();
+    type_str(&mut editor, "correcthorse");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::Normal);
+    assert_eq!(
+        persistence::load_encrypted(path, "correcthorse").unwrap(),
+        "note"
+    );
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_password_visibility_resets_on_reentry() {
+    let mut editor = Editor::new(None, None, None, false);
+    editor.toggle_password_visibility();
+    assert!(
+        !editor.show_password,
+        "Only toggles while entering a password"
+    );
+
+    editor.enter_save_mode();
+    type_str(&mut editor, "note.amnesio");
+    editor.handle_newline();
+    editor.toggle_password_visibility();
+    assert!(editor.show_password);
+
+    editor.exit_popup();
+    editor.enter_open_mode();
+    assert!(!editor.show_password);
+}
+
+#[test]
+fn test_idle_timeout_paused_in_popups() {
+    let mut editor = Editor::new(Some(0.05), None, None, false);
+    editor.enter_save_mode();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(!editor.is_timed_out());
+
+    // Leaving the popup restarts the idle clock.
+    editor.exit_popup();
+    assert!(!editor.is_timed_out());
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(editor.is_timed_out());
+}
+
+#[test]
+fn test_extend_ttl_up_to_limit() {
+    let mut editor = Editor::new(None, Some(10.0), None, false);
+    editor.max_ttl_extensions = Some(1);
+    let expiry = editor.ttl_expiry.unwrap();
+
+    editor.extend_ttl();
+    assert_eq!(
+        editor.ttl_expiry.unwrap() - expiry,
+        std::time::Duration::from_secs(600)
+    );
+
+    editor.extend_ttl();
+    assert_eq!(editor.ttl_extensions, 1);
+    assert_eq!(
+        editor.ttl_expiry.unwrap() - expiry,
+        std::time::Duration::from_secs(600)
+    );
+
+    let mut no_ttl = Editor::new(None, None, None, false);
+    no_ttl.extend_ttl();
+    assert!(no_ttl.ttl_expiry.is_none());
+}
+
+#[test]
+fn test_idle_lock_requires_unlock_password() {
+    let mut editor = Editor::new(None, None, Some([7u8; 32]), false);
+    editor.set_unlock_password("letmeback").unwrap();
+    type_str(&mut editor, "note");
+
+    editor.lock();
+    assert!(editor.input_mode == InputMode::Locked);
+    editor.exit_popup();
+    editor.enter_save_mode();
+    assert!(editor.input_mode == InputMode::Locked);
+
+    type_str(&mut editor, "wrong");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::Locked);
+    assert!(editor.password_buffer.is_empty());
+
+    type_str(&mut editor, "letmeback");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::Normal);
+    assert_eq!(editor.storage.to_string(), "note");
+}
+
+#[test]
+fn test_cursor_moves_over_whole_graphemes() {
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, &format!("a{}{}b", FAMILY, E_COMBINING));
+    let end = editor.cursor_position;
+
+    editor.move_cursor(-1); // before 'b'
+    editor.move_cursor(-1); // before the accented e
+    assert_eq!(editor.cursor_position, end - 1 - E_COMBINING.len());
+    editor.move_cursor(-1); // before the family emoji
+    assert_eq!(editor.cursor_position, 1);
+    editor.move_cursor(1);
+    assert_eq!(editor.cursor_position, 1 + FAMILY.len());
+}
+
+#[test]
+fn test_cursor_stays_on_grapheme_boundaries() {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    let mut editor = Editor::new(None, None, None, false);
+    let text = format!("a{}ç\n{}中", FAMILY, E_COMBINING);
+    type_str(&mut editor, &text);
+
+    let mut stops = vec![editor.cursor_position];
+    while editor.cursor_position > 0 {
+        editor.move_cursor(-1);
+        stops.push(editor.cursor_position);
+    }
+    stops.reverse();
+    let boundaries: Vec<usize> = text
+        .grapheme_indices(true)
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect();
+    assert_eq!(stops, boundaries);
+
+    // A cursor placed inside a character is moved back to its start.
+    editor.cursor_position = 2;
+    editor.move_cursor(1);
+    assert_eq!(editor.cursor_position, 1 + FAMILY.len());
+    editor.cursor_position = text.len() - 1;
+    editor.delete_backspace();
+    assert_eq!(editor.storage.to_string(), format!("a{}ç\n中", FAMILY));
+}
+
+#[test]
+fn test_backspace_deletes_whole_grapheme() {
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, &format!("a{}{}", FAMILY, E_COMBINING));
+
+    editor.delete_backspace();
+    assert_eq!(editor.storage.to_string(), format!("a{}", FAMILY));
+    editor.delete_backspace();
+    assert_eq!(editor.storage.to_string(), "a");
+    assert_eq!(editor.cursor_position, 1);
+}
+
+#[test]
+fn test_vertical_movement_follows_edits() {
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "first\n\nthird line");
+    editor.move_cursor_lineal(-1);
+    assert_eq!(editor.cursor_position, 6); // Empty middle line
+    editor.move_cursor_lineal(-1);
+    assert_eq!(editor.cursor_position, 0);
+    editor.move_cursor_lineal(-1);
+    assert_eq!(editor.cursor_position, 0);
+
+    // Lines added after the index was built are seen.
+    editor.move_to_end();
+    type_str(&mut editor, "\nfourth");
+    editor.move_cursor_lineal(-1);
+    assert_eq!(editor.cursor_position, 13);
+    editor.move_cursor_lineal(5);
+    assert_eq!(editor.cursor_position, 24);
+}
+
+#[test]
+fn test_vertical_movement_counts_graphemes() {
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, &format!("{}x\nabc", E_COMBINING));
+    // Cursor at end of "abc" (column 3); the line above has 2 graphemes.
+    editor.move_cursor_lineal(-1);
+    assert_eq!(editor.cursor_position, 4);
+    editor.move_cursor(-1);
+    editor.move_cursor_lineal(1);
+    // Column 1 on the second line.
+    assert_eq!(editor.cursor_position, 6);
+}
+
+#[test]
+fn test_display_column_counts_wide_chars() {
+    use amnesia::tui_app::display_position;
+
+    // "ab中文c": the CJK chars take two cells (and three bytes) each.
+    let text = "ab\u{4E2D}\u{6587}c\nx";
+    assert_eq!(display_position(text, 2, 4), (0, 2));
+    assert_eq!(display_position(text, 5, 4), (0, 4));
+    assert_eq!(display_position(text, 9, 4), (0, 7));
+    assert_eq!(display_position(text, 11, 4), (1, 1));
+}
+
+#[test]
+fn test_tabs_expand_to_tab_stops() {
+    use amnesia::tui_app::{display_position, expand_tabs};
+
+    let text = "a\tbc\td\n\tx";
+    assert_eq!(expand_tabs(text, 4), "a   bc  d\n    x");
+    assert_eq!(display_position(text, 2, 4), (0, 4));
+    assert_eq!(display_position(text, 5, 4), (0, 8));
+    assert_eq!(display_position(text, 8, 4), (1, 4));
+}
+
+#[test]
+fn test_long_lines_scroll_horizontally() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let mut terminal = Terminal::new(TestBackend::new(12, 6)).unwrap();
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "0123456789abcdefghij");
+
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    // 10 visible columns inside the border; cursor sits at column 20.
+    assert_eq!(editor.h_scroll_offset, 11);
+
+    for _ in 0..20 {
+        editor.move_cursor(-1);
+    }
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    assert_eq!(editor.h_scroll_offset, 0);
+}
+
+#[test]
+fn test_soft_wrap_rows_match_cursor_position() {
+    use amnesia::tui_app::{layout_text, visual_position};
+
+    let text = "abcdefg\nhi";
+    assert_eq!(layout_text(text, 4, Some(3)), "abc\ndef\ng\nhi");
+    assert_eq!(visual_position(text, 4, 4, Some(3)), (1, 1));
+    // Just past a full row, the cursor moves to the next row.
+    assert_eq!(visual_position(text, 6, 4, Some(3)), (2, 0));
+    assert_eq!(visual_position(text, 10, 4, Some(3)), (3, 2));
+
+    // Wide chars never straddle a row boundary.
+    assert_eq!(
+        layout_text("a\u{4E2D}\u{6587}", 4, Some(3)),
+        "a\u{4E2D}\n\u{6587}"
+    );
+}
+
+#[test]
+fn test_ttl_gauge_shown_only_with_ttl() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let status_row = |editor: &mut Editor| {
+        let mut terminal = Terminal::new(TestBackend::new(60, 6)).unwrap();
+        terminal.draw(|f| editor.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..60).map(|x| buffer[(x, 5)].symbol()).collect::<String>()
+    };
+
+    assert!(status_row(&mut Editor::new(None, Some(10.0), None, false)).contains("TTL 100%"));
+    assert!(!status_row(&mut Editor::new(None, None, None, false)).contains("TTL 1"));
+}
+
+#[test]
+fn test_status_bar_shows_buffer_usage() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let status_row = |editor: &mut Editor| {
+        let mut terminal = Terminal::new(TestBackend::new(80, 6)).unwrap();
+        terminal.draw(|f| editor.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..80).map(|x| buffer[(x, 5)].symbol()).collect::<String>()
+    };
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.status_format = "{mem}".into();
+    editor.insert_str(&"x".repeat(2150));
+    let row = status_row(&mut editor);
+    assert!(row.starts_with("2.1 KB / 64.0 KB"));
+    assert!(!row.contains("FULL"));
+
+    editor.insert_str(&"x".repeat(60 * 1024));
+    assert!(status_row(&mut editor).contains("[BUFFER 97% FULL]"));
+}
+
+#[test]
+fn test_max_buffer_bytes_refuses_further_input() {
+    let mut editor = Editor::new(None, None, None, false);
+    editor.max_buffer_bytes = Some(8);
+    type_str(&mut editor, "abcdef");
+    editor.insert_str("ghi");
+    assert_eq!(editor.storage.to_string(), "abcdef");
+    assert!(editor
+        .status_message
+        .as_ref()
+        .unwrap()
+        .0
+        .starts_with("Buffer full"));
+
+    editor.insert_str("gh");
+    editor.handle_input('€');
+    assert_eq!(editor.storage.to_string(), "abcdefgh");
+    assert_eq!(editor.cursor_position, 8);
+}
+
+#[test]
+fn test_full_buffer_can_replace_its_selection() {
+    let mut editor = Editor::new(None, None, None, false);
+    editor.max_buffer_bytes = Some(8);
+    type_str(&mut editor, "abcdefgh");
+
+    editor.select_all();
+    editor.insert_str("123456789");
+    assert_eq!(editor.storage.to_string(), "abcdefgh");
+    assert!(editor.selection().is_some());
+
+    editor.insert_str("1234");
+    assert_eq!(editor.storage.to_string(), "1234");
+
+    type_str(&mut editor, "5678");
+    editor.select_all();
+    editor.handle_input('x');
+    assert_eq!(editor.storage.to_string(), "x");
+}
+
+#[test]
+fn test_insert_file_at_cursor() {
+    let path = std::env::temp_dir().join("amnesia_test_insert_file.txt");
+    std::fs::write(&path, "snippet\r\nline").unwrap();
+    let status = |editor: &Editor| editor.status_message.as_ref().unwrap().0.clone();
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.insert_str("ab");
+    editor.move_cursor(-1);
+    editor.enter_insert_file_mode();
+    editor.insert_str(path.to_str().unwrap());
+    editor.handle_newline();
+    assert_eq!(editor.storage.to_string(), "asnippet\nlineb");
+    assert_eq!(editor.cursor_position, 13);
+    assert!(status(&editor).contains("still on disk, unencrypted"));
+    assert!(path.exists());
+
+    // The cap is checked before the file is read.
+    editor.max_buffer_bytes = Some(20);
+    editor.enter_insert_file_mode();
+    editor.insert_str(path.to_str().unwrap());
+    editor.handle_newline();
+    assert_eq!(editor.storage.to_string(), "asnippet\nlineb");
+    assert!(status(&editor).starts_with("Buffer full"));
+
+    // Devices and directories have no meaningful size to check.
+    editor.max_buffer_bytes = None;
+    for special in ["/dev/zero".into(), std::env::temp_dir()] {
+        editor.enter_insert_file_mode();
+        editor.insert_str(special.to_str().unwrap());
+        editor.handle_newline();
+        assert_eq!(editor.storage.to_string(), "asnippet\nlineb");
+    }
+    assert!(status(&editor).contains("not a regular file"));
+
+    editor.read_only = true;
+    editor.enter_insert_file_mode();
+    assert!(editor.input_mode == InputMode::Normal);
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_relative_line_numbers() {
+    use amnesia::config::LineNumbers;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let rows = |editor: &mut Editor| {
+        let mut terminal = Terminal::new(TestBackend::new(20, 7)).unwrap();
+        terminal.draw(|f| editor.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (1..5)
+            .map(|y| (1..12).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+    };
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.wrap = true;
+    editor.insert_str("one\ntwo\nthree and a long tail\nfour");
+    editor.cursor_position = 5; // On "two"
+
+    editor.cycle_line_numbers();
+    assert_eq!(editor.line_numbers, LineNumbers::Absolute);
+    assert_eq!(
+        rows(&mut editor),
+        ["  1 one    ", "  2 two    ", "  3 three a", "    ng tail"]
+    );
+
+    editor.cycle_line_numbers();
+    assert_eq!(
+        rows(&mut editor),
+        ["  1 one    ", "  2 two    ", "  1 three a", "    ng tail"]
+    );
+
+    editor.cycle_line_numbers();
+    assert_eq!(rows(&mut editor)[0], "one        ");
+}
+
+#[test]
+fn test_ruler_is_drawn_at_column_past_text() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let row = |editor: &mut Editor, y: u16| {
+        let mut terminal = Terminal::new(TestBackend::new(20, 6)).unwrap();
+        terminal.draw(|f| editor.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (1..19).map(|x| buffer[(x, y)].symbol()).collect::<String>()
+    };
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.insert_str("short\nmuch longer line");
+    editor.cursor_position = 0;
+    assert_eq!(row(&mut editor, 1), "short             ");
+
+    editor.ruler = Some(10);
+    assert_eq!(row(&mut editor, 1), "short    │        ");
+    assert_eq!(row(&mut editor, 2), "much longer line  ");
+    assert_eq!(row(&mut editor, 3), "         │        ");
+
+    // The ruler follows horizontal scrolling.
+    editor.ruler = Some(20);
+    assert_eq!(row(&mut editor, 3), "                  ");
+    editor.move_to_end();
+    editor.insert_str("  and more");
+    assert!(row(&mut editor, 3).contains('│'));
+}
+
+#[test]
+fn test_delete_word_backward() {
+    let mut editor = Editor::new(None, None, None, false);
+    editor.insert_str("first line\nsay don't  panic, ok");
+
+    editor.delete_word_backward();
+    assert_eq!(editor.storage.to_string(), "first line\nsay don't  panic, ");
+    editor.delete_word_backward();
+    assert_eq!(editor.storage.to_string(), "first line\nsay don't  panic");
+    editor.delete_word_backward();
+    editor.delete_word_backward();
+    assert_eq!(editor.storage.to_string(), "first line\nsay ");
+    editor.delete_word_backward();
+    assert_eq!(editor.storage.to_string(), "first line\n");
+    assert_eq!(editor.cursor_position, 11);
+
+    // At the start of a line only the line break goes.
+    editor.delete_word_backward();
+    assert_eq!(editor.storage.to_string(), "first line");
+
+    // Multi-byte words go whole; from the middle of a word, only its start.
+    editor.insert_str(" café");
+    editor.move_cursor(-2);
+    editor.delete_word_backward();
+    assert_eq!(editor.storage.to_string(), "first line fé");
+    assert_eq!(editor.cursor_position, 11);
+
+    editor.read_only = true;
+    editor.delete_word_backward();
+    assert_eq!(editor.storage.to_string(), "first line fé");
+}
+
+#[test]
+fn test_select_all_then_operate() {
+    use ratatui::backend::TestBackend;
+    use ratatui::style::Modifier;
+    use ratatui::Terminal;
+
+    let selected = |editor: &mut Editor, y: u16| {
+        let mut terminal = Terminal::new(TestBackend::new(20, 6)).unwrap();
+        terminal.draw(|f| editor.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (1..19)
+            .filter(|&x| buffer[(x, y)].modifier.contains(Modifier::REVERSED))
+            .count()
+    };
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.insert_str("one\ntwo");
+    editor.move_to_start();
+    editor.select_all();
+    assert_eq!(editor.selection(), Some(0..7));
+    assert_eq!(editor.cursor_position, 7);
+    assert_eq!(selected(&mut editor, 1), 18);
+    assert_eq!(selected(&mut editor, 2), 3);
+
+    // An arrow collapses it to the matching end, without moving further.
+    editor.move_cursor(-1);
+    assert_eq!(editor.selection(), None);
+    assert_eq!(editor.cursor_position, 0);
+    assert_eq!(selected(&mut editor, 1), 0);
+
+    // Typing replaces it, in one edit.
+    editor.select_all();
+    type_str(&mut editor, "x");
+    assert_eq!(editor.storage.to_string(), "x");
+    assert_eq!(editor.selection(), None);
+
+    // Backspace deletes exactly the selection.
+    editor.select_all();
+    editor.delete_backspace();
+    assert_eq!(editor.storage.to_string(), "");
+
+    // Read-only notes can be selected but not changed.
+    editor.insert_str("keep");
+    editor.read_only = true;
+    editor.select_all();
+    editor.delete_backspace();
+    assert_eq!(editor.storage.to_string(), "keep");
+}
+
+#[test]
+fn test_redaction_masks_text_but_keeps_layout() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let row = |editor: &mut Editor, y: u16| {
+        let mut terminal = Terminal::new(TestBackend::new(20, 6)).unwrap();
+        terminal.draw(|f| editor.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (1..19).map(|x| buffer[(x, y)].symbol()).collect::<String>()
+    };
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.insert_str("pin 1234\n\tkey");
+    editor.toggle_redaction();
+    assert_eq!(row(&mut editor, 1), "••• ••••          ");
+    assert_eq!(row(&mut editor, 2), "    •••           ");
+
+    // Only drawing changes: typing still edits the note.
+    type_str(&mut editor, "s");
+    assert_eq!(editor.storage.to_string(), "pin 1234\n\tkeys");
+    assert_eq!(row(&mut editor, 2), "    ••••          ");
+
+    editor.toggle_redaction();
+    assert_eq!(row(&mut editor, 1), "pin 1234          ");
+}
+
+#[test]
+fn test_scrolling_past_u16_rows() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let mut terminal = Terminal::new(TestBackend::new(20, 6)).unwrap();
+    let mut editor = Editor::new(None, None, None, false);
+    let lines: String = (0..70_000).map(|i| format!("{}\n", i)).collect();
+    editor.insert_str(&lines);
+    type_str(&mut editor, "end");
+
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    // 3 rows inside the border; the cursor is on row 70000.
+    assert_eq!(editor.scroll_offset, 69_998);
+    let buffer = terminal.backend().buffer();
+    let row = |y: u16| (1..7).map(|x| buffer[(x, y)].symbol()).collect::<String>();
+    assert_eq!(row(1), "69998 ");
+    assert_eq!(row(3), "end   ");
+
+    editor.enter_goto_mode();
+    type_str(&mut editor, "65540");
+    editor.handle_newline();
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    assert_eq!(editor.scroll_offset, 65_539);
+    let buffer = terminal.backend().buffer();
+    assert_eq!(buffer[(1, 1)].symbol(), "6");
+    assert_eq!(buffer[(5, 1)].symbol(), "9");
+}
+
+#[test]
+fn test_status_format_substitutes_known_tokens() {
+    let values = [("line", "3".to_string()), ("words", "42".to_string())];
+    assert_eq!(
+        format_status("{line} | {words}w | {nope} | {", &values),
+        "3 | 42w | {nope} | {"
+    );
+}
+
+#[test]
+fn test_custom_status_format_is_drawn() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let mut terminal = Terminal::new(TestBackend::new(40, 6)).unwrap();
+    let mut editor = Editor::new(None, None, None, false);
+    editor.status_format = "{words} words, line {line}".into();
+    type_str(&mut editor, "three little words");
+    terminal.draw(|f| editor.draw(f)).unwrap();
+
+    let buffer = terminal.backend().buffer();
+    let row: String = (0..40).map(|x| buffer[(x, 5)].symbol()).collect();
+    assert!(row.starts_with("3 words, line 1"));
+}
+
+#[test]
+fn test_goto_line_clamps_and_rejects_text() {
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "one\ntwo\nthree");
+
+    editor.enter_goto_mode();
+    type_str(&mut editor, "2");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::Normal);
+    assert_eq!(editor.cursor_position, 4);
+
+    editor.enter_goto_mode();
+    type_str(&mut editor, "99");
+    editor.handle_newline();
+    assert_eq!(editor.cursor_position, 8);
+
+    editor.enter_goto_mode();
+    type_str(&mut editor, "0");
+    editor.handle_newline();
+    assert_eq!(editor.cursor_position, 0);
+
+    editor.enter_goto_mode();
+    type_str(&mut editor, "two");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::GotoLine);
+    assert_eq!(editor.cursor_position, 0);
+    assert!(editor.status_message.is_some());
+}
+
+#[test]
+fn test_status_timeout_is_configurable() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use std::time::Duration;
+
+    let mut terminal = Terminal::new(TestBackend::new(40, 6)).unwrap();
+    let mut editor = Editor::new(None, None, None, false);
+    editor.status_timeout = Duration::from_millis(20);
+    editor.set_status("Saved");
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    assert!(editor.status_message.is_some());
+
+    std::thread::sleep(Duration::from_millis(30));
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    assert!(editor.status_message.is_none());
+
+    editor.status_timeout = Duration::from_secs(60);
+    editor.set_status("Error: disk full");
+    std::thread::sleep(Duration::from_millis(30));
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    assert!(editor.status_message.is_some());
+}
+
+#[test]
+fn test_jump_to_top_and_bottom_scrolls() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let mut terminal = Terminal::new(TestBackend::new(20, 6)).unwrap();
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, &"line\n".repeat(20));
+
+    editor.move_to_start();
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    assert_eq!(editor.cursor_position, 0);
+    assert_eq!(editor.scroll_offset, 0);
+
+    editor.move_to_end();
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    assert_eq!(editor.cursor_position, 100);
+    assert!(editor.scroll_offset > 0);
+
+    editor.enter_goto_mode();
+    editor.move_to_start();
+    assert_eq!(editor.cursor_position, 100, "No-op while a popup is open");
+}
+
+#[test]
+fn test_insert_str_advances_cursor_by_bytes() {
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "ad");
+    editor.move_cursor(-1);
+    editor.insert_str("bç");
+    assert_eq!(editor.storage.to_string(), "abçd");
+    assert_eq!(editor.cursor_position, 4);
+
+    editor.enter_save_mode();
+    editor.insert_str("notes.amnesio");
+    assert_eq!(editor.path_buffer, "notes.amnesio");
+    assert_eq!(editor.storage.to_string(), "abçd");
+}
+
+#[test]
+fn test_clipboard_copy_needs_confirmation() {
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "hi");
+
+    assert!(editor.copy_to_clipboard().is_none());
+    assert_eq!(
+        editor.copy_to_clipboard().as_deref(),
+        Some("\x1b]52;c;aGk=\x07")
+    );
+    // Confirmation is used up. The prompt names the key actually bound.
+    use amnesia::keys::{Action, KeySpec};
+    editor
+        .key_bindings
+        .set(Action::CopyClipboard, KeySpec::parse("ctrl+y").unwrap());
+    assert!(editor.copy_to_clipboard().is_none());
+    assert!(editor
+        .status_message
+        .as_ref()
+        .unwrap()
+        .0
+        .starts_with("Press Ctrl+Y again"));
+}
+
+#[test]
+fn test_read_only_ignores_edits() {
+    let mut editor = Editor::new(None, None, None, true);
+    editor.storage.update("fixed");
+    editor.cursor_position = 5;
+
+    editor.handle_input('x');
+    editor.handle_newline();
+    editor.delete_backspace();
+    editor.insert_str("pasted");
+    assert_eq!(editor.storage.to_string(), "fixed");
+    assert_eq!(editor.cursor_position, 5);
+
+    editor.enter_save_mode();
+    assert!(editor.input_mode == InputMode::Normal);
+}
+
+#[test]
+fn test_format_size() {
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(1229), "1.2 KB");
+    assert_eq!(format_size(3 * 1024 * 1024 + 400 * 1024), "3.4 MB");
+}
+
+#[test]
+fn test_generate_random_filename() {
+    let mut editor = Editor::new(None, None, None, false);
+    editor.generate_filename();
+    assert!(editor.path_buffer.is_empty()); // Only in the save popup
+
+    editor.enter_save_mode();
+    type_str(&mut editor, "notes/diary.amnesio");
+    editor.generate_filename();
+    let name = editor
+        .path_buffer
+        .strip_prefix("notes/")
+        .and_then(|n| n.strip_suffix(".amnesio"))
+        .expect("directory and extension kept");
+    assert_eq!(name.len(), 16);
+    assert!(name.chars().all(|c| c.is_ascii_hexdigit()));
+
+    let first = editor.path_buffer.clone();
+    editor.generate_filename();
+    assert_ne!(editor.path_buffer, first);
+    assert!(editor.input_mode == InputMode::EnterPath);
+}
+
+#[test]
+fn test_save_rejects_missing_directory_before_password() {
+    let mut editor = Editor::new(None, None, None, false);
+    editor.enter_save_mode();
+    type_str(&mut editor, "no_such_dir_for_amnesia/note");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::EnterPath);
+    let (status, _) = editor.status_message.clone().unwrap();
+    assert_eq!(status, "Directory not found: no_such_dir_for_amnesia");
+
+    editor.path_buffer = "note".to_string();
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::EnterPassword);
+}
+
+#[test]
+fn test_existing_save_path_asks_before_overwriting() {
+    let path = "test_editor_overwrite.amnesio";
+    std::fs::write(path, b"another note").unwrap();
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.enter_save_mode();
+    type_str(&mut editor, "test_editor_overwrite");
+    editor.handle_newline();
+    assert!(editor.input_mode == InputMode::ConfirmOverwrite);
+
+    editor.handle_input('x');
+    assert!(editor.input_mode == InputMode::ConfirmOverwrite);
+    editor.handle_input('n');
+    assert!(editor.input_mode == InputMode::EnterPath);
+    assert_eq!(editor.path_buffer, "test_editor_overwrite");
+
+    editor.handle_newline();
+    editor.handle_input('y');
+    assert!(editor.input_mode == InputMode::EnterPassword);
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_tab_completes_paths() {
+    let dir = "test_complete_dir";
+    std::fs::remove_dir_all(dir).ok();
+    std::fs::create_dir_all(format!("{}/journal", dir)).unwrap();
+    for name in [
+        "diary-2024.amnesio",
+        "diary-2025.amnesio",
+        ".hidden.amnesio",
+    ] {
+        std::fs::write(format!("{}/{}", dir, name), b"").unwrap();
+    }
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.enter_open_mode();
+    type_str(&mut editor, &format!("{}/d", dir));
+    editor.complete_path();
+    assert_eq!(editor.path_buffer, format!("{}/diary-202", dir));
+    assert_eq!(
+        editor.path_candidates(),
+        ["diary-2024.amnesio", "diary-2025.amnesio"]
+    );
+
+    type_str(&mut editor, "5");
+    assert!(editor.path_candidates().is_empty());
+    editor.complete_path();
+    assert_eq!(editor.path_buffer, format!("{}/diary-2025.amnesio", dir));
+
+    editor.path_buffer = format!("{}/j", dir);
+    editor.complete_path();
+    assert_eq!(
+        editor.path_buffer,
+        format!("{}/journal{}", dir, std::path::MAIN_SEPARATOR)
+    );
+
+    editor.path_buffer = format!("{}/.", dir);
+    editor.complete_path();
+    assert_eq!(editor.path_buffer, format!("{}/.hidden.amnesio", dir));
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_tabs_keep_separate_notes() {
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "first");
+    editor.new_tab();
+    assert_eq!(editor.tab_count(), 2);
+    assert_eq!(editor.active_tab(), 1);
+    assert_eq!(editor.storage.to_string(), "");
+    type_str(&mut editor, "second");
+
+    editor.new_tab();
+    type_str(&mut editor, "third");
+    editor.switch_tab(0);
+    assert_eq!(editor.storage.to_string(), "first");
+    assert_eq!(editor.cursor_position, 5);
+    editor.next_tab();
+    assert_eq!(editor.storage.to_string(), "second");
+    editor.switch_tab(2);
+    editor.next_tab();
+    assert_eq!(editor.active_tab(), 0);
+    editor.switch_tab(7); // No such tab
+    assert_eq!(editor.active_tab(), 0);
+
+    // Closing a tab with text needs a second press, then shows its neighbour.
+    editor.switch_tab(1);
+    editor.close_tab();
+    assert_eq!(editor.tab_count(), 3);
+    editor.close_tab();
+    assert_eq!(editor.tab_count(), 2);
+    assert_eq!(editor.active_tab(), 1);
+    assert_eq!(editor.storage.to_string(), "third");
+    editor.switch_tab(0);
+    assert_eq!(editor.storage.to_string(), "first");
+
+    editor.wipe();
+    assert_eq!(editor.tab_count(), 1);
+    assert_eq!(editor.storage.to_string(), "");
+}
+
+#[test]
+fn test_clear_asks_only_for_unsaved_changes() {
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "draft");
+    assert!(editor.is_dirty());
+    editor.request_clear();
+    assert!(editor.input_mode == InputMode::ConfirmClear);
+    editor.handle_input('n');
+    assert!(editor.input_mode == InputMode::Normal);
+    assert_eq!(editor.storage.to_string(), "draft");
+
+    editor.request_clear();
+    editor.handle_input('y');
+    assert!(editor.input_mode == InputMode::Normal);
+    assert_eq!(editor.storage.to_string(), "");
+    assert_eq!(editor.cursor_position, 0);
+
+    // A note that was just saved or opened clears straight away.
+    type_str(&mut editor, "saved");
+    editor.mark_saved();
+    assert!(!editor.is_dirty());
+    editor.request_clear();
+    assert!(editor.input_mode == InputMode::Normal);
+    assert_eq!(editor.storage.to_string(), "");
+}
+
+#[test]
+fn test_autosave_writes_changed_notes() {
+    let path = "test_editor_autosave.amnesio";
+    let password = "supersecretpassword888";
+    std::fs::remove_file(path).ok();
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.autosave_if_due(); // Off by default
+    assert!(!Path::new(path).exists());
+
+    assert!(editor.set_autosave(path.into(), std::time::Duration::ZERO, password));
+    type_str(&mut editor, "draft one");
+    editor.autosave_if_due();
+    assert_eq!(editor.status_message.clone().unwrap().0, "Auto-saved");
+    assert_eq!(
+        persistence::load_encrypted(path, password).unwrap(),
+        "draft one"
+    );
+
+    // Unchanged notes aren't written again.
+    editor.status_message = None;
+    editor.autosave_if_due();
+    assert!(editor.status_message.is_none());
+
+    type_str(&mut editor, ", two");
+    editor.autosave_if_due();
+    assert_eq!(
+        persistence::load_encrypted(path, password).unwrap(),
+        "draft one, two"
+    );
+
+    assert!(!editor.set_autosave(path.into(), std::time::Duration::ZERO, &"x".repeat(300)));
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_autosave_stays_with_its_tab() {
+    let path = "test_editor_autosave_tabs.amnesio";
+    let password = "supersecretpassword888";
+    std::fs::remove_file(path).ok();
+
+    let mut editor = Editor::new(None, None, None, false);
+    assert!(editor.set_autosave(path.into(), std::time::Duration::ZERO, password));
+    type_str(&mut editor, "first");
+    editor.autosave_if_due();
+
+    // Another tab's note never replaces the auto-save.
+    editor.new_tab();
+    type_str(&mut editor, "second note");
+    editor.autosave_if_due();
+    assert_eq!(
+        persistence::load_encrypted(path, password).unwrap(),
+        "first"
+    );
+
+    // Back on the auto-saved note, its own changes are still picked up.
+    editor.switch_tab(0);
+    type_str(&mut editor, " edit");
+    editor.autosave_if_due();
+    assert_eq!(
+        persistence::load_encrypted(path, password).unwrap(),
+        "first edit"
+    );
+
+    // Closing its tab turns auto-save off.
+    editor.close_tab();
+    editor.close_tab();
+    type_str(&mut editor, "!");
+    editor.autosave_if_due();
+    assert_eq!(
+        persistence::load_encrypted(path, password).unwrap(),
+        "first edit"
+    );
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_mixed_line_endings_are_normalized_and_restored_on_save() {
+    let path = "test_editor_crlf.amnesio";
+    let password = "supersecretpassword888";
+    std::fs::remove_file(path).ok();
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.insert_str("one\r\ntwo\rthree\nfour\r\n");
+    assert_eq!(editor.storage.to_string(), "one\ntwo\nthree\nfour\n");
+    assert_eq!(editor.cursor_position, 19);
+
+    editor.crlf_on_save = true;
+    assert!(editor.set_autosave(path.into(), std::time::Duration::ZERO, password));
+    editor.autosave_if_due();
+    assert_eq!(
+        persistence::load_encrypted(path, password).unwrap(),
+        "one\r\ntwo\r\nthree\r\nfour\r\n"
+    );
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_help_overlay_ignores_input_but_not_idle() {
+    let mut editor = Editor::new(Some(0.05), None, None, false);
+    editor.toggle_help();
+    assert!(editor.input_mode == InputMode::Help);
+
+    type_str(&mut editor, "abc");
+    editor.handle_newline();
+    editor.delete_backspace();
+    assert_eq!(editor.storage.to_string(), "");
+    // Leaving the help open doesn't hold off the idle timeout.
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(editor.is_idle());
+
+    editor.toggle_help();
+    assert!(editor.input_mode == InputMode::Normal);
+
+    // Only opens from the editor itself.
+    editor.enter_goto_mode();
+    editor.toggle_help();
+    assert!(editor.input_mode == InputMode::GotoLine);
+}
+
+#[test]
+fn test_current_line_highlight() {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "one\ntwo\nthree");
+    editor.move_cursor_lineal(-1);
+    let current = editor.theme.current_line;
+
+    let row_bgs = |editor: &mut Editor| {
+        let mut terminal = Terminal::new(TestBackend::new(20, 7)).unwrap();
+        terminal.draw(|f| editor.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (1..4).map(|y| buffer[(5, y)].bg).collect::<Vec<_>>()
+    };
+
+    assert!(!row_bgs(&mut editor).contains(&current));
+    editor.highlight_current_line = true;
+    let bgs = row_bgs(&mut editor);
+    assert_ne!(bgs[0], current);
+    assert_eq!(bgs[1], current); // "two", past the end of the text too
+    assert_ne!(bgs[2], current);
+}
+
+#[test]
+fn test_whitespace_display() {
+    use ratatui::backend::TestBackend;
+    use ratatui::style::Color;
+    use ratatui::Terminal;
+
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "a\tb c\nd  ");
+    editor.toggle_whitespace();
+
+    let mut terminal = Terminal::new(TestBackend::new(20, 6)).unwrap();
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let row = |y: u16| (1..9).map(|x| buffer[(x, y)].symbol()).collect::<String>();
+    assert_eq!(row(1), "a→  b·c ");
+    assert_eq!(row(2), "d··     ");
+    // Only the trailing spaces are flagged.
+    assert_ne!(buffer[(6, 1)].bg, Color::Red);
+    assert_eq!(buffer[(2, 2)].bg, Color::Red);
+    assert_eq!(buffer[(3, 2)].bg, Color::Red);
+
+    // The buffer itself is unchanged.
+    assert_eq!(editor.storage.to_string(), "a\tb c\nd  ");
+}
+
+#[test]
+fn test_markdown_blockquotes() {
+    use amnesia::tui_app::render_markdown;
+    use ratatui::style::Modifier;
+
+    let lines = render_markdown(
+        "> quoted **bold**\n>> nested\n> > also nested\nplain > not",
+        10,
+    );
+    assert_eq!(lines.len(), 4);
+
+    let text = |i: usize| {
+        lines[i]
+            .spans
+            .iter()
+            .map(|s| s.content.as_ref())
+            .collect::<String>()
+    };
+    assert_eq!(text(0), "│ quoted bold");
+    assert!(lines[0].spans[1].style.add_modifier.contains(Modifier::DIM));
+    assert!(lines[0].spans[2]
+        .style
+        .add_modifier
+        .contains(Modifier::BOLD));
+    assert_eq!(text(1), "│ │ nested");
+    assert_eq!(text(2), "│ │ also nested");
+    assert_eq!(text(3), "plain > not");
+}
+
+#[test]
+fn test_markdown_horizontal_rules() {
+    use amnesia::tui_app::render_markdown;
+
+    let lines = render_markdown("---\n***\n_ _ _\n--\n- item\n**b**", 8);
+    let text = |i: usize| {
+        lines[i]
+            .spans
+            .iter()
+            .map(|s| s.content.as_ref())
+            .collect::<String>()
+    };
+    for i in 0..3 {
+        assert_eq!(text(i), "─".repeat(8));
+    }
+    assert_eq!(text(3), "--");
+    assert_eq!(text(4), "- item");
+    assert_eq!(text(5), "b");
+}
+
+#[test]
+fn test_markdown_strikethrough_and_inline_code() {
+    use amnesia::tui_app::render_markdown;
+    use ratatui::style::Modifier;
+
+    let lines = render_markdown("~~old~~ **bold ~~both~~** `a **b**` ~~open", 40);
+    let spans = &lines[0].spans;
+    let parts: Vec<&str> = spans.iter().map(|s| s.content.as_ref()).collect();
+    assert_eq!(
+        parts,
+        ["old", " ", "bold ", "both", " ", "a **b**", " ~~open"]
+    );
+
+    let mods = |i: usize| spans[i].style.add_modifier;
+    assert!(mods(0).contains(Modifier::CROSSED_OUT));
+    assert!(!mods(1).intersects(Modifier::CROSSED_OUT | Modifier::BOLD));
+    assert!(mods(2).contains(Modifier::BOLD) && !mods(2).contains(Modifier::CROSSED_OUT));
+    assert!(mods(3).contains(Modifier::BOLD | Modifier::CROSSED_OUT));
+    // Code is literal and styled apart from the surrounding text.
+    assert!(!mods(5).contains(Modifier::BOLD));
+    assert_ne!(spans[5].style.bg, spans[4].style.bg);
+    // An unclosed marker stays as written.
+    assert!(!mods(6).contains(Modifier::CROSSED_OUT));
+}
+
+#[test]
+fn test_markdown_header_levels() {
+    use amnesia::tui_app::render_markdown;
+    use ratatui::style::{Color, Modifier};
+
+    let content = "# a\n## b\n### c\n#### d\n##### e\n###### f\n####### g\n#nospace";
+    let lines = render_markdown(content, 10);
+    let fg = |i: usize| lines[i].spans[0].style.fg;
+    assert_eq!(fg(0), Some(Color::Cyan));
+    assert_eq!(fg(1), Some(Color::Blue));
+    assert_eq!(fg(2), Some(Color::Green));
+    for i in 3..6 {
+        assert!(fg(i).is_some(), "h{} is styled", i + 1);
+    }
+    assert!(lines[3].spans[0]
+        .style
+        .add_modifier
+        .contains(Modifier::BOLD));
+    assert!(!lines[4].spans[0]
+        .style
+        .add_modifier
+        .contains(Modifier::BOLD));
+    // Seven hashes or no space isn't a header.
+    assert_eq!(lines[6].spans[0].style.fg, None);
+    assert_eq!(lines[7].spans[0].style.fg, None);
+}
+
+#[test]
+fn test_split_preview_renders_both_panes() {
+    use ratatui::backend::TestBackend;
+    use ratatui::style::Color;
+    use ratatui::Terminal;
+
+    let mut editor = Editor::new(None, None, None, false);
+    type_str(&mut editor, "# Title\nbody");
+    editor.show_markdown = true; // Ignored on the left while split
+    editor.toggle_split_preview();
+
+    let mut terminal = Terminal::new(TestBackend::new(40, 6)).unwrap();
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let text = |x: u16, y: u16, len: u16| {
+        (x..x + len)
+            .map(|x| buffer[(x, y)].symbol())
+            .collect::<String>()
+    };
+    assert_eq!(text(1, 1, 7), "# Title");
+    assert_eq!(text(21, 1, 7), "# Title");
+    assert_eq!(buffer[(1, 1)].fg, editor.theme.editor_fg);
+    assert_eq!(buffer[(21, 1)].fg, Color::Cyan);
+    // The cursor stays in the left pane.
+    terminal.backend_mut().assert_cursor_position((5, 2));
+
+    type_str(&mut editor, "!");
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    let buffer = terminal.backend().buffer();
+    assert_eq!(buffer[(25, 2)].symbol(), "!");
+}
+
+#[test]
+fn test_reading_time_estimate() {
+    use amnesia::tui_app::reading_time;
+
+    assert_eq!(reading_time(0, 200), "");
+    assert_eq!(reading_time(30, 200), "1 min");
+    assert_eq!(reading_time(700, 200), "4 min");
+    assert_eq!(reading_time(700, 100), "7 min");
+
+    let mut editor = Editor::new(None, None, None, false);
+    editor.status_format = "[{read}] {words}w".into();
+    editor.reading_wpm = 2;
+    let status = |editor: &mut Editor| {
+        use ratatui::backend::TestBackend;
+        use ratatui::Terminal;
+        let mut terminal = Terminal::new(TestBackend::new(30, 5)).unwrap();
+        terminal.draw(|f| editor.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..30).map(|x| buffer[(x, 4)].symbol()).collect::<String>()
+    };
+    assert!(status(&mut editor).starts_with("[] 0w"));
+    type_str(&mut editor, "one two three four five");
+    assert!(status(&mut editor).starts_with("[3 min] 5w"));
+    type_str(&mut editor, " six");
+    assert!(status(&mut editor).starts_with("[3 min] 6w"));
+}
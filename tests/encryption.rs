@@ -3,7 +3,7 @@ use amnesia::mem_buffer::MemoryBuffer;
 #[test]
 fn test_encryption_scrambles_data() {
     let key = [0u8; 32];
-    let mut buffer = MemoryBuffer::new(1024, Some(key));
+    let mut buffer = MemoryBuffer::new(1024, Some(key)).unwrap();
     let secret = "This is a secret message";
     buffer.update(secret);
 
@@ -15,10 +15,10 @@ fn test_encryption_scrambles_data() {
 fn test_different_keys_different_ciphertext() {
     let secret = "Same secret message";
 
-    let mut buffer1 = MemoryBuffer::new(1024, Some([1u8; 32]));
+    let mut buffer1 = MemoryBuffer::new(1024, Some([1u8; 32])).unwrap();
     buffer1.update(secret);
 
-    let mut buffer2 = MemoryBuffer::new(1024, Some([2u8; 32]));
+    let mut buffer2 = MemoryBuffer::new(1024, Some([2u8; 32])).unwrap();
     buffer2.update(secret);
 
     // In an integration test, we can't easily check the internal scrambling
@@ -29,8 +29,151 @@ fn test_different_keys_different_ciphertext() {
 
 #[test]
 fn test_no_encryption_works() {
-    let mut buffer = MemoryBuffer::new(1024, None);
+    let mut buffer = MemoryBuffer::new(1024, None).unwrap();
     let msg = "Normal message";
     buffer.update(msg);
     assert_eq!(buffer.to_string(), msg);
 }
+
+#[test]
+fn test_shrinking_and_growing_content() {
+    let mut buffer = MemoryBuffer::new(16, Some([3u8; 32])).unwrap();
+    buffer.update("a fairly long line of text");
+    assert_eq!(buffer.to_string(), "a fairly long line of text");
+
+    buffer.update("short");
+    assert_eq!(buffer.to_string(), "short");
+
+    buffer.update("short and then much longer than the original capacity");
+    assert_eq!(
+        buffer.to_string(),
+        "short and then much longer than the original capacity"
+    );
+}
+
+#[test]
+fn test_insert_and_remove_chars_in_place() {
+    for key in [None, Some([9u8; 32])] {
+        let mut buffer = MemoryBuffer::new(8, key).unwrap();
+        buffer.update("héllo");
+
+        buffer.insert_char(1, 'ü');
+        assert_eq!(buffer.to_string(), "hüéllo");
+
+        // Inserting past the end appends, and growing keeps the content.
+        for c in " wörld".chars() {
+            buffer.insert_char(usize::MAX, c);
+        }
+        assert_eq!(buffer.to_string(), "hüéllo wörld");
+
+        assert_eq!(buffer.remove_char(2), Some('é'));
+        assert_eq!(buffer.to_string(), "hüllo wörld");
+        assert_eq!(buffer.remove_char(100), None);
+        assert_eq!(buffer.byte_offset(2), 3);
+    }
+}
+
+#[test]
+fn test_length_accessors_track_edits() {
+    let mut buffer = MemoryBuffer::new(1024, Some([5u8; 32])).unwrap();
+    assert_eq!((buffer.char_len(), buffer.byte_len()), (0, 0));
+
+    buffer.update("añb");
+    assert_eq!((buffer.char_len(), buffer.byte_len()), (3, 4));
+
+    buffer.insert_char(3, '€');
+    assert_eq!((buffer.char_len(), buffer.byte_len()), (4, 7));
+
+    buffer.remove_char(1);
+    assert_eq!((buffer.char_len(), buffer.byte_len()), (3, 5));
+
+    buffer.wipe();
+    assert_eq!((buffer.char_len(), buffer.byte_len()), (0, 0));
+}
+
+#[test]
+fn test_embedded_nul_is_preserved() {
+    for key in [None, Some([3u8; 32])] {
+        let mut buffer = MemoryBuffer::new(1024, key).unwrap();
+        buffer.update("before\0after");
+        assert_eq!(buffer.to_string(), "before\0after");
+
+        buffer.insert_str(6, "\0\0");
+        assert_eq!(buffer.to_string(), "before\0\0\0after");
+        assert_eq!(buffer.char_len(), 14);
+    }
+}
+
+#[test]
+fn test_byte_offset_edits_stay_on_char_boundaries() {
+    for key in [None, Some([9u8; 32])] {
+        let mut buffer = MemoryBuffer::new(1024, key).unwrap();
+        buffer.update("añb€");
+        buffer.insert_str_at(3, "x");
+        assert_eq!(buffer.to_string(), "añxb€");
+
+        // Offsets inside a character move back to its start.
+        buffer.insert_str_at(2, "y");
+        assert_eq!(buffer.to_string(), "ayñxb€");
+        buffer.remove_range(3..8);
+        assert_eq!(buffer.to_string(), "ay€");
+        assert_eq!((buffer.char_len(), buffer.byte_len()), (3, 5));
+
+        buffer.remove_range(2..100);
+        assert_eq!(buffer.to_string(), "ay");
+        buffer.insert_str_at(100, "z");
+        assert_eq!(buffer.to_string(), "ayz");
+    }
+}
+
+#[test]
+fn test_capacity_and_used_bytes() {
+    let mut buffer = MemoryBuffer::new(16, Some([5u8; 32])).unwrap();
+    assert_eq!((buffer.used_bytes(), buffer.capacity()), (0, 16));
+
+    buffer.update("añb");
+    assert_eq!((buffer.used_bytes(), buffer.capacity()), (4, 16));
+
+    // Growing at least doubles the allocation.
+    buffer.update(&"x".repeat(20));
+    assert_eq!((buffer.used_bytes(), buffer.capacity()), (20, 32));
+}
+
+#[test]
+fn test_key_rotation_preserves_content() {
+    // Longer than one 256-byte rotation chunk.
+    let secret = "Rotate me. ".repeat(40);
+    let mut buffer = MemoryBuffer::new(16, Some([3u8; 32])).unwrap();
+    buffer.update(&secret);
+    buffer.rotate_key(&[4u8; 32]);
+    assert_eq!(buffer.to_string(), secret);
+
+    // Edits after rotation use the new key consistently.
+    buffer.insert_char(0, '>');
+    buffer.remove_char(1);
+    buffer.rotate_key(&[5u8; 32]);
+    assert_eq!(buffer.to_string(), format!(">{}", &secret[1..]));
+}
+
+#[test]
+fn test_new_buffer_is_locked() {
+    let mut buffer = MemoryBuffer::new(16, None).expect("Failed to lock a small buffer");
+    assert!(buffer.is_locked());
+
+    // Growing re-pins the new allocation.
+    buffer.update(&"x".repeat(64));
+    assert!(buffer.is_locked());
+}
+
+#[test]
+fn test_insert_str_in_place_and_when_growing() {
+    let mut buffer = MemoryBuffer::new(16, Some([6u8; 32])).unwrap();
+    buffer.update("héllo");
+    buffer.insert_str(1, "ß€");
+    assert_eq!(buffer.to_string(), "hß€éllo");
+    assert_eq!(buffer.char_len(), 7);
+
+    // Past the current capacity.
+    buffer.insert_str(7, &" world".repeat(5));
+    assert_eq!(buffer.to_string(), format!("hß€éllo{}", " world".repeat(5)));
+}
@@ -0,0 +1,22 @@
+use amnesia::export::write_html;
+
+#[test]
+fn test_export_renders_markdown_document() {
+    let mut out = Vec::new();
+    write_html(
+        &mut out,
+        "# Plan\n\n- [x] ~~done~~\n- **bold** `code`\n\n| a | b |\n|---|---|\n| 1 | 2 |\n",
+        "Plan <draft>",
+    )
+    .unwrap();
+    let html = String::from_utf8(out).unwrap();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Plan &lt;draft&gt;</title>"));
+    assert!(html.contains("<h1>Plan</h1>"));
+    assert!(html.contains("<del>done</del>"));
+    assert!(html.contains("type=\"checkbox\""));
+    assert!(html.contains("<strong>bold</strong> <code>code</code>"));
+    assert!(html.contains("<table>"));
+    assert!(html.trim_end().ends_with("</html>"));
+}
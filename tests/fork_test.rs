@@ -0,0 +1,28 @@
+// Its own test binary: it forks, which is only safe with few threads about.
+#![cfg(target_os = "linux")]
+
+use amnesia::mem_buffer::{LockedString, MemoryBuffer};
+
+#[test]
+fn test_freed_buffers_leave_the_heap_usable_in_forked_children() {
+    // Small enough for the heap rather than a separate mapping, so the
+    // allocator reuses the freed pages.
+    for _ in 0..8 {
+        let mut buffer = MemoryBuffer::new_lenient(48 * 1024, None);
+        buffer.update("secret");
+        let mut cache = LockedString::new();
+        cache.set(&"x".repeat(48 * 1024));
+    }
+    let reused = vec![1u8; 256 * 1024];
+
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let sum: u64 = reused.iter().map(|&b| u64::from(b)).sum();
+        unsafe { libc::_exit(if sum == reused.len() as u64 { 0 } else { 1 }) };
+    }
+    let mut status = 0;
+    unsafe { libc::waitpid(pid, &mut status, 0) };
+    assert!(libc::WIFEXITED(status), "child died with status {}", status);
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+}
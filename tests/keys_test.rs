@@ -0,0 +1,82 @@
+use amnesia::keys::{Action, KeyBindings, KeySpec};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[test]
+fn test_parse_key_specs() {
+    assert_eq!(
+        KeySpec::parse("ctrl+\\"),
+        Some(KeySpec::new(KeyCode::Char('\\'), KeyModifiers::CONTROL))
+    );
+    assert_eq!(
+        KeySpec::parse("Alt+Backspace"),
+        Some(KeySpec::new(KeyCode::Backspace, KeyModifiers::ALT))
+    );
+    assert_eq!(
+        KeySpec::parse("f1"),
+        Some(KeySpec::new(KeyCode::F(1), KeyModifiers::NONE))
+    );
+    assert_eq!(KeySpec::parse("hyper+x"), None);
+    assert_eq!(KeySpec::parse("ctrl+xy"), None);
+}
+
+#[test]
+fn test_legacy_ctrl_backslash_matches() {
+    let spec = KeySpec::parse("ctrl+\\").unwrap();
+    // Most terminals report Ctrl+\ as Ctrl+4.
+    let legacy = KeyEvent::new(KeyCode::Char('4'), KeyModifiers::CONTROL);
+    assert!(spec.matches(&legacy));
+    let plain = KeyEvent::new(KeyCode::Char('\\'), KeyModifiers::NONE);
+    assert!(!spec.matches(&plain));
+}
+
+#[test]
+fn test_default_bindings_match_builtin_keys() {
+    let bindings = KeyBindings::default();
+    let ctrl_s = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+    assert_eq!(bindings.action_for(&ctrl_s), Some(Action::Save));
+    let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+    assert_eq!(bindings.action_for(&esc), Some(Action::Quit));
+    let ctrl_w = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL);
+    assert_eq!(
+        bindings.action_for(&ctrl_w),
+        Some(Action::DeleteWordBackward)
+    );
+    let plain_s = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE);
+    assert_eq!(bindings.action_for(&plain_s), None);
+    assert!(bindings.conflicts().is_empty());
+
+    for action in Action::ALL {
+        assert_eq!(Action::from_name(action.name()), Some(action));
+    }
+}
+
+#[test]
+fn test_rebinding_and_display() {
+    let mut bindings = KeyBindings::default();
+    bindings.set(Action::Quit, KeySpec::parse("ctrl+q").unwrap());
+    let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+    assert_eq!(bindings.action_for(&esc), None);
+    let ctrl_q = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL);
+    assert_eq!(bindings.action_for(&ctrl_q), Some(Action::Quit));
+
+    bindings.set(Action::Open, bindings.key(Action::Save));
+    assert_eq!(bindings.conflicts(), vec![(Action::Save, Action::Open)]);
+
+    assert_eq!(bindings.key(Action::Save).to_string(), "Ctrl+S");
+    assert_eq!(bindings.key(Action::Help).to_string(), "F1");
+    assert_eq!(bindings.key(Action::Bottom).to_string(), "Ctrl+End");
+}
+
+#[test]
+fn test_goto_tab_bindings() {
+    let mut bindings = KeyBindings::default();
+    let alt_3 = KeyEvent::new(KeyCode::Char('3'), KeyModifiers::ALT);
+    let action = bindings.action_for(&alt_3);
+    assert_eq!(action, Some(Action::GotoTab3));
+    assert_eq!(action.and_then(Action::tab_index), Some(2));
+    assert_eq!(Action::from_name("tab_9"), Some(Action::GotoTab9));
+    assert_eq!(bindings.goto_tab_range().as_deref(), Some("Alt+1..9"));
+
+    bindings.set(Action::GotoTab2, KeySpec::parse("f2").unwrap());
+    assert_eq!(bindings.goto_tab_range(), None);
+}
@@ -11,7 +11,11 @@ fn test_persistence_full_cycle() {
     fs::remove_file(path).ok();
 
     // 1. Save
-    persistence::save_encrypted(path, content, password).expect("Save failed");
+    let info = persistence::save_encrypted(path, content, password, true).expect("Save failed");
+    assert_eq!(info.path, std::path::Path::new(path));
+    assert_eq!(info.bytes_written, fs::metadata(path).unwrap().len());
+    assert_eq!(info.version, persistence::CURRENT_VERSION);
+    assert_eq!(info.kdf, Some(persistence::KdfParams::current()));
 
     // 2. Load
     let loaded = persistence::load_encrypted(path, password).expect("Load failed");
@@ -19,8 +23,571 @@ fn test_persistence_full_cycle() {
 
     // 3. Wrong Password
     let result = persistence::load_encrypted(path, "wrongpassword");
-    assert!(result.is_err());
+    assert_eq!(result, Err(persistence::PersistenceError::DecryptionFailed));
 
     // 4. Cleanup
     fs::remove_file(path).ok();
 }
+
+#[test]
+fn test_shred_file_removes_readonly_file() {
+    let path = "test_shred.amnesio";
+    fs::remove_file(path).ok();
+
+    persistence::save_encrypted(path, "shred me", "supersecretpassword888", true)
+        .expect("Save failed");
+    persistence::shred_file(path).expect("Shred failed");
+    assert!(!std::path::Path::new(path).exists());
+}
+
+#[test]
+fn test_failed_write_leaves_original_intact() {
+    let dir = "test_atomic_dir";
+    fs::remove_dir_all(dir).ok();
+    fs::create_dir(dir).unwrap();
+    let path = format!("{}/note.amnesio", dir);
+
+    persistence::save_encrypted(&path, "original", "supersecretpassword888", true)
+        .expect("Save failed");
+
+    let result = persistence::write_atomic(&path, |file| {
+        use std::io::Write;
+        file.write_all(b"partial")?;
+        Err(std::io::Error::other("simulated crash"))
+    });
+    assert!(result.is_err());
+
+    let loaded = persistence::load_encrypted(&path, "supersecretpassword888").expect("Load failed");
+    assert_eq!(loaded, "original");
+    // No temporary file is left behind.
+    assert_eq!(fs::read_dir(dir).unwrap().count(), 1);
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_read_only_output_is_optional() {
+    let path = "test_read_only_output.amnesio";
+    fs::remove_file(path).ok();
+
+    persistence::save_encrypted(path, "v1", "supersecretpassword888", false).expect("Save failed");
+    assert!(!fs::metadata(path).unwrap().permissions().readonly());
+
+    persistence::save_encrypted(path, "v2", "supersecretpassword888", true).expect("Save failed");
+    assert!(fs::metadata(path).unwrap().permissions().readonly());
+
+    persistence::shred_file(path).ok();
+}
+
+#[test]
+fn test_rechange_password_round_trip() {
+    let path = "test_rechange_password.amnesio";
+    fs::remove_file(path).ok();
+
+    persistence::save_encrypted(path, "keep me", "oldpassword1", true).expect("Save failed");
+    persistence::rechange_password(path, "oldpassword1", "newpassword2").expect("Passwd failed");
+
+    let loaded = persistence::load_encrypted(path, "newpassword2").expect("Load failed");
+    assert_eq!(loaded, "keep me");
+    assert_eq!(
+        persistence::load_encrypted(path, "oldpassword1"),
+        Err(persistence::PersistenceError::DecryptionFailed)
+    );
+    assert!(fs::metadata(path).unwrap().permissions().readonly());
+
+    persistence::shred_file(path).ok();
+}
+
+#[test]
+fn test_verify_checks_password_and_integrity() {
+    let path = "test_verify.amnesio";
+    fs::remove_file(path).ok();
+    persistence::save_encrypted(path, "backup", "supersecretpassword888", false)
+        .expect("Save failed");
+
+    persistence::verify(path, "supersecretpassword888").expect("Verify failed");
+    assert!(matches!(
+        persistence::verify(path, "wrongpassword"),
+        Err(persistence::PersistenceError::DecryptionFailed)
+    ));
+
+    // Flip the last ciphertext byte.
+    let mut bytes = fs::read(path).unwrap();
+    *bytes.last_mut().unwrap() ^= 0x01;
+    fs::write(path, bytes).unwrap();
+    assert!(matches!(
+        persistence::verify(path, "supersecretpassword888"),
+        Err(persistence::PersistenceError::DecryptionFailed)
+    ));
+
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_metadata_round_trip() {
+    let path = "test_metadata.amnesio";
+    fs::remove_file(path).ok();
+
+    let content = "\n# Shopping list\n- eggs\n";
+    persistence::save_encrypted(path, content, "supersecretpassword888", false)
+        .expect("Save failed");
+
+    let (loaded, metadata) =
+        persistence::load_encrypted_with_metadata(path, "supersecretpassword888")
+            .expect("Load failed");
+    assert_eq!(loaded, content);
+    let metadata = metadata.expect("v3 files carry metadata");
+    assert_eq!(metadata.title.as_deref(), Some("Shopping list"));
+    assert!(metadata.created > 0);
+
+    // The title is not readable from the file without the password.
+    let raw = fs::read(path).unwrap();
+    assert!(!raw.windows(8).any(|w| w == b"Shopping"));
+
+    fs::remove_file(path).ok();
+}
+
+/// Writes a file in the v2 layout: plain content, no metadata block.
+fn write_v2_file(path: &str, content: impl AsRef<[u8]>, password: &str) {
+    use argon2::{password_hash::SaltString, Argon2};
+    use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+
+    let salt_bytes = [1u8; 16];
+    let nonce_bytes = [2u8; 12];
+    let salt = SaltString::encode_b64(&salt_bytes).unwrap();
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt.as_str().as_bytes(), &mut key)
+        .unwrap();
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .encrypt(Nonce::from_slice(&nonce_bytes), content.as_ref())
+        .unwrap();
+
+    let mut file = b"AMNESIO2".to_vec();
+    file.extend_from_slice(&salt_bytes);
+    file.extend_from_slice(&nonce_bytes);
+    file.extend_from_slice(&ciphertext);
+    fs::write(path, file).unwrap();
+}
+
+#[test]
+fn test_v2_files_load_and_migrate() {
+    let path = "test_migrate_v2.amnesio";
+    let password = "supersecretpassword888";
+    write_v2_file(path, "old note", password);
+
+    assert_eq!(persistence::file_version(path).unwrap(), 2);
+    let (content, metadata) = persistence::load_encrypted_with_metadata(path, password).unwrap();
+    assert_eq!(content, "old note");
+    assert!(metadata.is_none());
+
+    assert!(persistence::migrate(path, password).expect("Migration failed"));
+    assert_eq!(
+        persistence::file_version(path).unwrap(),
+        persistence::CURRENT_VERSION
+    );
+    assert_eq!(
+        persistence::load_encrypted(path, password).unwrap(),
+        "old note"
+    );
+    assert!(!persistence::migrate(path, password).unwrap());
+
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_lossy_load_replaces_invalid_utf8() {
+    let path = "test_lossy.amnesio";
+    let password = "supersecretpassword888";
+    write_v2_file(path, b"caf\xe9 au lait", password);
+
+    assert_eq!(
+        persistence::load_encrypted(path, password),
+        Err(persistence::PersistenceError::Encryption(
+            "Decrypted content is not valid UTF-8".into()
+        ))
+    );
+    let (content, damaged) = persistence::load_encrypted_lossy(path, password).unwrap();
+    assert_eq!(content, "caf\u{FFFD} au lait");
+    assert!(damaged);
+
+    write_v2_file(path, "café au lait", password);
+    assert_eq!(
+        persistence::load_encrypted_lossy(path, password).unwrap(),
+        ("café au lait".to_string(), false)
+    );
+
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_file_info_reads_the_header_without_a_password() {
+    use persistence::{KdfParams, KeySource, PersistenceError};
+
+    let path = "test_file_info.amnesio";
+    let password = "supersecretpassword888";
+    write_v2_file(path, "old note", password);
+
+    let info = persistence::file_info(path).unwrap();
+    assert_eq!(info.version, 2);
+    assert_eq!(info.key_source, KeySource::Password);
+    assert_eq!(info.cipher, "ChaCha20-Poly1305");
+    assert_eq!(info.assumed_kdf, Some(KdfParams::current()));
+    assert!(!info.chunked);
+    assert!(!info.header_authenticated);
+
+    persistence::migrate(path, password).unwrap();
+    let info = persistence::file_info(path).unwrap();
+    assert_eq!(info.version, persistence::CURRENT_VERSION);
+    assert!(info.header_authenticated);
+
+    assert_eq!(
+        persistence::file_info("Cargo.toml"),
+        Err(PersistenceError::InvalidFileFormat)
+    );
+
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_unknown_versions_are_reported() {
+    let path = "test_unknown_version.amnesio";
+    for (magic, version) in [(b"AMNESIO1", 1), (b"AMNESIO9", 9)] {
+        let mut file = magic.to_vec();
+        file.extend_from_slice(&[0u8; 64]);
+        fs::write(path, file).unwrap();
+        assert!(matches!(
+            persistence::load_encrypted(path, "whatever1"),
+            Err(persistence::PersistenceError::UnsupportedVersion(v)) if v == version
+        ));
+    }
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_truncated_file_is_not_a_wrong_password() {
+    use persistence::PersistenceError;
+
+    let path = "test_truncated.amnesio";
+    let password = "supersecretpassword888";
+    fs::remove_file(path).ok();
+    persistence::save_encrypted(path, "short note", password, false).unwrap();
+    let original = fs::read(path).unwrap();
+
+    assert_eq!(
+        persistence::load_encrypted(path, "wrongpassword"),
+        Err(PersistenceError::DecryptionFailed)
+    );
+
+    // Cut inside the tag, then inside the header.
+    let header = 8 + 1 + 16 + 12;
+    for len in [header + 15, header - 3, 4] {
+        fs::write(path, &original[..len]).unwrap();
+        assert_eq!(
+            persistence::load_encrypted(path, password),
+            Err(PersistenceError::InvalidFileFormat),
+            "truncated to {} bytes",
+            len
+        );
+    }
+
+    // A flipped magic byte isn't an .amnesio file at all.
+    let mut flipped = original.clone();
+    flipped[0] ^= 0x20;
+    fs::write(path, &flipped).unwrap();
+    assert_eq!(
+        persistence::load_encrypted(path, password),
+        Err(PersistenceError::InvalidFileFormat)
+    );
+
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_header_tampering_fails_authentication() {
+    let path = "test_header_aad.amnesio";
+    let password = "supersecretpassword888";
+    fs::remove_file(path).ok();
+    persistence::save_encrypted(path, "bound", password, false).unwrap();
+    let original = fs::read(path).unwrap();
+
+    // Flip a salt byte.
+    let mut tampered = original.clone();
+    tampered[9] ^= 0x01;
+    fs::write(path, &tampered).unwrap();
+    assert!(matches!(
+        persistence::load_encrypted(path, password),
+        Err(persistence::PersistenceError::DecryptionFailed)
+    ));
+
+    // Downgrade the version to one without AAD.
+    let mut downgraded = original.clone();
+    downgraded[7] = b'3';
+    fs::write(path, &downgraded).unwrap();
+    assert!(matches!(
+        persistence::load_encrypted(path, password),
+        Err(persistence::PersistenceError::DecryptionFailed)
+    ));
+
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_large_notes_use_chunked_frames() {
+    let path = "test_chunked.amnesio";
+    let password = "supersecretpassword888";
+    fs::remove_file(path).ok();
+
+    // Not a multiple of the frame size, so the last frame is short.
+    let content = "0123456789abcdef\n".repeat(100_000);
+    assert!(content.len() > persistence::CHUNK_THRESHOLD);
+    let info = persistence::save_encrypted(path, &content, password, false).expect("Save failed");
+    assert_eq!(info.version, persistence::CHUNKED_VERSION);
+    assert_eq!(
+        persistence::file_version(path).unwrap(),
+        persistence::CHUNKED_VERSION
+    );
+
+    let (loaded, metadata) =
+        persistence::load_encrypted_with_metadata(path, password).expect("Load failed");
+    assert_eq!(loaded, content);
+    assert_eq!(metadata.unwrap().title.as_deref(), Some("0123456789abcdef"));
+    assert!(!persistence::migrate(path, password).unwrap());
+
+    // Changing the password keeps the chunked format.
+    persistence::rechange_password(path, password, "newpassword2").expect("Passwd failed");
+    assert_eq!(
+        persistence::load_encrypted(path, "newpassword2").unwrap(),
+        content
+    );
+
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_chunked_truncation_and_reordering_fail() {
+    let path = "test_chunked_tamper.amnesio";
+    let password = "supersecretpassword888";
+    fs::remove_file(path).ok();
+    persistence::save_encrypted(path, &"x".repeat(2 * 1024 * 1024), password, false).unwrap();
+    let original = fs::read(path).unwrap();
+    let header = 8 + 1 + 16 + 7;
+    let frame = 64 * 1024 + 16;
+
+    // Dropping the final frame leaves a file ending on a frame boundary.
+    // The first frame still authenticates, so this is damage, not a wrong
+    // password.
+    fs::write(
+        path,
+        &original[..original.len() - (original.len() - header) % frame],
+    )
+    .unwrap();
+    assert_eq!(
+        persistence::load_encrypted(path, password),
+        Err(persistence::PersistenceError::InvalidFileFormat)
+    );
+    assert_eq!(
+        persistence::load_encrypted(path, "wrongpassword"),
+        Err(persistence::PersistenceError::DecryptionFailed)
+    );
+
+    // Swapping the first two frames.
+    let mut swapped = original.clone();
+    let (first, second) = (header..header + frame, header + frame..header + 2 * frame);
+    let first_frame = original[first.clone()].to_vec();
+    swapped.copy_within(second, header);
+    swapped[first.end..first.end + frame].copy_from_slice(&first_frame);
+    fs::write(path, &swapped).unwrap();
+    assert!(matches!(
+        persistence::load_encrypted(path, password),
+        Err(persistence::PersistenceError::DecryptionFailed)
+    ));
+
+    fs::remove_file(path).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_scrub_on_overwrite_wipes_old_file() {
+    let path = "test_scrub.amnesio";
+    let link = "test_scrub_old.amnesio";
+    let password = "supersecretpassword888";
+    fs::remove_file(path).ok();
+    fs::remove_file(link).ok();
+
+    persistence::save_encrypted(path, "first draft", password, true).unwrap();
+    // A second name keeps the old file reachable after it is replaced.
+    fs::hard_link(path, link).unwrap();
+    let old = fs::read(link).unwrap();
+
+    let metadata = persistence::FileMetadata::for_content("second draft");
+    persistence::save_encrypted_with_metadata(
+        path,
+        "second draft",
+        password,
+        true,
+        true,
+        &metadata,
+    )
+    .expect("Save failed");
+    assert_eq!(
+        persistence::load_encrypted(path, password).unwrap(),
+        "second draft"
+    );
+
+    let scrubbed = fs::read(link).unwrap();
+    assert_eq!(scrubbed.len(), old.len());
+    assert_ne!(scrubbed, old);
+    assert_eq!(
+        persistence::load_encrypted(link, password)
+            .unwrap_err()
+            .kind(),
+        persistence::ErrorKind::InvalidFileFormat
+    );
+    assert!(fs::metadata(link).unwrap().permissions().readonly());
+
+    persistence::shred_file(path).ok();
+    persistence::shred_file(link).ok();
+}
+
+#[test]
+fn test_keyfile_round_trip() {
+    use persistence::{Credential, KeySource, PersistenceError};
+
+    let path = "test_keyfile.amnesio";
+    let keyfile = "test_keyfile.key";
+    fs::remove_file(path).ok();
+    fs::write(keyfile, [7u8; 64]).unwrap();
+
+    let mut key = persistence::read_keyfile(keyfile).unwrap();
+    let metadata = persistence::FileMetadata::for_content("locked by a file");
+    persistence::save_with_credential(
+        path,
+        "locked by a file",
+        Credential::Keyfile(&key),
+        false,
+        false,
+        &metadata,
+    )
+    .expect("Save failed");
+    assert_eq!(persistence::key_source(path).unwrap(), KeySource::Keyfile);
+
+    let (loaded, _) =
+        persistence::load_with_credential(path, Credential::Keyfile(&key)).expect("Load failed");
+    assert_eq!(loaded, "locked by a file");
+
+    // Another keyfile fails, and a password is refused before decrypting.
+    key[0] ^= 0x01;
+    assert!(matches!(
+        persistence::load_with_credential(path, Credential::Keyfile(&key)),
+        Err(PersistenceError::DecryptionFailed)
+    ));
+    assert!(matches!(
+        persistence::load_encrypted(path, "supersecretpassword888"),
+        Err(PersistenceError::WrongKeySource(KeySource::Keyfile))
+    ));
+
+    // The key source byte is authenticated.
+    let mut bytes = fs::read(path).unwrap();
+    bytes[8] = 0;
+    fs::write(path, &bytes).unwrap();
+    assert_eq!(
+        persistence::load_encrypted(path, "supersecretpassword888"),
+        Err(PersistenceError::DecryptionFailed)
+    );
+
+    fs::write(keyfile, b"short").unwrap();
+    assert!(matches!(
+        persistence::read_keyfile(keyfile),
+        Err(PersistenceError::InvalidKeyfile(_))
+    ));
+
+    fs::remove_file(path).ok();
+    fs::remove_file(keyfile).ok();
+}
+
+#[test]
+fn test_age_recipients_round_trip() {
+    use age::secrecy::ExposeSecret;
+    use persistence::{KeySource, PersistenceError};
+
+    let path = "test_recipients.amnesio";
+    let identity_path = "test_recipients.key";
+    fs::remove_file(path).ok();
+
+    let identity = age::x25519::Identity::generate();
+    let other = age::x25519::Identity::generate();
+    fs::write(identity_path, identity.to_string().expose_secret()).unwrap();
+
+    let info = persistence::save_to_recipients(
+        path,
+        "for your eyes only",
+        &[other.to_public(), identity.to_public()],
+    )
+    .expect("Save failed");
+    assert_eq!(info.kdf, None);
+    assert_eq!(
+        persistence::key_source(path).unwrap(),
+        KeySource::Recipients
+    );
+
+    let (loaded, metadata) =
+        persistence::load_with_identity(path, identity_path).expect("Load failed");
+    assert_eq!(loaded, "for your eyes only");
+    assert_eq!(
+        metadata.unwrap().title.as_deref(),
+        Some("for your eyes only")
+    );
+    assert!(matches!(
+        persistence::load_encrypted(path, "supersecretpassword888"),
+        Err(PersistenceError::WrongKeySource(KeySource::Recipients))
+    ));
+
+    // An identity that isn't a recipient can't open it.
+    fs::write(
+        identity_path,
+        age::x25519::Identity::generate()
+            .to_string()
+            .expose_secret(),
+    )
+    .unwrap();
+    assert!(matches!(
+        persistence::load_with_identity(path, identity_path),
+        Err(PersistenceError::DecryptionFailed)
+    ));
+
+    persistence::shred_file(path).ok();
+    fs::remove_file(identity_path).ok();
+}
+
+#[test]
+fn test_persistence_failures_compare_by_value() {
+    use persistence::{ErrorKind, PersistenceError};
+    use std::io;
+
+    let missing = persistence::load_encrypted("test_no_such_file.amnesio", "password");
+    assert_eq!(
+        missing,
+        Err(PersistenceError::Io(io::Error::from(
+            io::ErrorKind::NotFound
+        )))
+    );
+    assert_eq!(missing.unwrap_err().kind(), ErrorKind::Io);
+
+    assert_eq!(
+        PersistenceError::UnsupportedVersion(9),
+        PersistenceError::UnsupportedVersion(9)
+    );
+    assert_ne!(
+        PersistenceError::UnsupportedVersion(9),
+        PersistenceError::UnsupportedVersion(2)
+    );
+    assert_ne!(
+        PersistenceError::InvalidFileFormat,
+        PersistenceError::DecryptionFailed
+    );
+    assert_eq!(
+        PersistenceError::Encryption("x".into()).kind(),
+        ErrorKind::Encryption
+    );
+}
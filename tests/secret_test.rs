@@ -0,0 +1,39 @@
+use amnesia::secret::{SecretBuffer, SECRET_CAPACITY};
+
+#[test]
+fn test_push_pop_roundtrip() {
+    let mut secret = SecretBuffer::new();
+    for c in "pässword".chars() {
+        assert!(secret.push(c));
+    }
+    assert_eq!(secret.as_str(), "pässword");
+
+    assert_eq!(secret.pop(), Some('d'));
+    assert_eq!(secret.as_str(), "pässwor");
+
+    secret.clear();
+    assert!(secret.is_empty());
+    assert_eq!(secret.pop(), None);
+}
+
+#[test]
+fn test_push_refuses_past_capacity() {
+    let mut secret = SecretBuffer::new();
+    for _ in 0..SECRET_CAPACITY {
+        assert!(secret.push('a'));
+    }
+    assert!(!secret.push('a'));
+    assert_eq!(secret.len(), SECRET_CAPACITY);
+}
+
+#[test]
+fn test_read_all_zeroizing_reads_everything() {
+    use amnesia::secret::{into_string_zeroizing, read_all_zeroizing};
+
+    let input = "line\n".repeat(5000);
+    let bytes = read_all_zeroizing(std::io::Cursor::new(input.as_bytes())).unwrap();
+    assert_eq!(into_string_zeroizing(bytes), input);
+
+    let invalid = read_all_zeroizing(&b"ok\xffok"[..]).unwrap();
+    assert_eq!(into_string_zeroizing(invalid), "ok\u{FFFD}ok");
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "spellcheck")]
+
+use amnesia::spell::{words, Dictionary};
+use amnesia::tui_app::Editor;
+
+fn dictionary() -> Dictionary {
+    ["the", "cat", "sat", "don't", "Paris", "note"]
+        .into_iter()
+        .collect()
+}
+
+#[test]
+fn test_word_ranges() {
+    let text = "Don't stop, 'quoted' v2 x";
+    let found: Vec<&str> = words(text).into_iter().map(|(s, e)| &text[s..e]).collect();
+    assert_eq!(found, ["Don't", "stop", "quoted", "v2", "x"]);
+}
+
+#[test]
+fn test_misspelled_words() {
+    let dictionary = dictionary();
+    assert!(!dictionary.is_misspelled("The"));
+    assert!(!dictionary.is_misspelled("Paris"));
+    assert!(!dictionary.is_misspelled("cat's"));
+    assert!(!dictionary.is_misspelled("don't"));
+    assert!(dictionary.is_misspelled("teh"));
+    assert!(dictionary.is_misspelled("paris"));
+    // Single letters and words with digits are skipped.
+    assert!(!dictionary.is_misspelled("x"));
+    assert!(!dictionary.is_misspelled("v2"));
+}
+
+#[test]
+fn test_misspelled_words_are_underlined() {
+    use ratatui::backend::TestBackend;
+    use ratatui::style::Modifier;
+    use ratatui::Terminal;
+
+    let mut editor = Editor::new(None, None, None, false);
+    for c in "the cta sat".chars() {
+        editor.handle_input(c);
+    }
+    editor.toggle_spell_check();
+    assert!(!editor.spell_check, "no dictionary loaded yet");
+
+    editor.dictionary = Some(dictionary());
+    editor.toggle_spell_check();
+    assert!(editor.spell_check);
+
+    let mut terminal = Terminal::new(TestBackend::new(20, 5)).unwrap();
+    terminal.draw(|f| editor.draw(f)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let underlined = |x: u16| buffer[(x, 1)].modifier.contains(Modifier::UNDERLINED);
+    // "the cta sat" starts at column 1 inside the border.
+    assert!(!underlined(1) && !underlined(3));
+    assert!(underlined(5) && underlined(6) && underlined(7));
+    assert!(!underlined(9));
+}
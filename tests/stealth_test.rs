@@ -0,0 +1,22 @@
+use amnesia::stealth;
+
+#[test]
+fn test_derive_key_without_path() {
+    // No external binaries may be needed to derive the key.
+    std::env::set_var("PATH", "");
+
+    let first = stealth::derive_key();
+    let second = stealth::derive_key();
+    assert_ne!(first, [0u8; 32]);
+    assert_ne!(first, second, "Keys include fresh startup randomness");
+}
+
+#[test]
+fn test_seeded_key_is_stable_within_the_process() {
+    let seed = [42u8; 32];
+    let first = stealth::derive_key_with_seed(&seed);
+    // Work in between must not change what the seed derives to.
+    let _ = stealth::derive_key();
+    assert_eq!(stealth::derive_key_with_seed(&seed), first);
+    assert_ne!(stealth::derive_key_with_seed(&[43u8; 32]), first);
+}
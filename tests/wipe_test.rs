@@ -0,0 +1,17 @@
+// Its own test binary: `wipe_all` zeroes every live buffer in the process.
+use amnesia::mem_buffer::{self, LockedString, MemoryBuffer};
+
+#[test]
+fn test_wipe_all_zeroes_buffers_and_plaintext_caches() {
+    let mut buffer = MemoryBuffer::new_lenient(64, None);
+    buffer.update("in the buffer");
+    let mut cache = LockedString::new();
+    cache.set("decrypted copy");
+    // Regrowing moves the cache to a new allocation, which must be tracked too.
+    cache.set(&"x".repeat(200));
+
+    mem_buffer::wipe_all();
+
+    assert!(cache.as_str().bytes().all(|b| b == 0));
+    assert!(buffer.to_string().bytes().all(|b| b == 0));
+}